@@ -1,8 +1,10 @@
 use std::fmt::Display;
 
-use crate::braille;
+use termion::color;
 
-#[derive(Debug, Clone, Copy)]
+use crate::{braille, font};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Pixel {
     pub r: u8,
     pub g: u8,
@@ -10,11 +12,27 @@ pub struct Pixel {
     pub a: u8,
 }
 
+impl Pixel {
+    pub fn is_lit(&self) -> bool {
+        ((self.r | self.g | self.b) & self.a) > 0
+    }
+}
+
+/// Most frequent colour among the lit dots of a braille character, since a
+/// terminal cell can only have one foreground colour.
+fn dominant(dots: &[Option<Pixel>]) -> Option<Pixel> {
+    dots.iter()
+        .flatten()
+        .max_by_key(|p| dots.iter().flatten().filter(|q| q == p).count())
+        .copied()
+}
+
 #[derive(Debug)]
 pub struct Canvas {
     width: usize,
     height: usize,
     pixels: Vec<Pixel>,
+    pub colored: bool,
 }
 
 impl Canvas {
@@ -31,6 +49,7 @@ impl Canvas {
                 };
                 (width * height) as usize
             ],
+            colored: false,
         }
     }
 
@@ -38,7 +57,6 @@ impl Canvas {
         self.pixels[(x + y * self.width) as usize] = pixel;
     }
 
-    #[allow(dead_code)]
     pub fn draw_line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, pixel: Pixel) {
         let dx = (x1 as isize - x0 as isize).abs();
         let sx = if x0 < x1 { 1 } else { -1 };
@@ -63,6 +81,32 @@ impl Canvas {
             }
         }
     }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, pixel: Pixel) {
+        for y in y..y + height {
+            self.draw_line(x, y, x + width - 1, y, pixel);
+        }
+    }
+
+    /// Draws a line of text from the top left corner `x`, `y` in the built-in
+    /// font, each font pixel a `size` x `size` square.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, size: usize, pixel: Pixel) {
+        for (i, c) in text.chars().enumerate() {
+            let left = x + i * (font::WIDTH + 1) * size;
+            for (dy, row) in font::glyph(c).iter().enumerate() {
+                for (dx, _) in row.chars().enumerate().filter(|&(_, dot)| dot == '#') {
+                    self.fill_rect(left + dx * size, y + dy * size, size, size, pixel);
+                }
+            }
+        }
+    }
+
+    /// Coordinates of the lit pixels, row by row.
+    pub fn lit(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.pixels.len())
+            .filter(|&i| self.pixels[i].is_lit())
+            .map(|i| (i % self.width, i / self.width))
+    }
 }
 
 impl Display for Canvas {
@@ -112,18 +156,21 @@ impl Display for Canvas {
                     } else {
                         None
                     },
-                ]
-                .iter()
-                .map(|&i| {
-                    if let Some(i) = i {
-                        let pixel = self.pixels[i as usize];
-                        (((pixel.r | pixel.g | pixel.b) & pixel.a) > 0) as u8
-                    } else {
-                        0
+                ];
+                let lit = braille
+                    .iter()
+                    .map(|&i| i.map(|i| self.pixels[i]).filter(Pixel::is_lit))
+                    .collect::<Vec<Option<Pixel>>>();
+                if self.colored {
+                    if let Some(pixel) = dominant(&lit) {
+                        s.push_str(&format!("{}", color::Fg(color::Rgb(pixel.r, pixel.g, pixel.b))));
                     }
-                })
-                .collect::<Vec<u8>>();
-                s.push(braille::Braille::from(braille.as_slice()).0);
+                }
+                let dots = lit.iter().map(|p| p.is_some() as u8).collect::<Vec<u8>>();
+                s.push(braille::Braille::from(dots.as_slice()).0);
+            }
+            if self.colored {
+                s.push_str(&format!("{}", color::Fg(color::Reset)));
             }
             s.push('║');
             s.push('\n');
@@ -0,0 +1,57 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// An asciinema v2 recording of what the text mode prints: a JSON header,
+/// then one `[seconds, "o", text]` line per write.
+pub struct Cast {
+    out: BufWriter<File>,
+    start: Instant,
+}
+
+impl Cast {
+    /// Starts a recording of a `width` x `height` character terminal.
+    pub fn create(path: &Path, width: usize, height: usize) -> std::io::Result<Cast> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let header = serde_json::json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": timestamp,
+            "env": { "TERM": "xterm-256color" },
+        });
+        writeln!(out, "{}", header)?;
+        Ok(Cast { out, start: Instant::now() })
+    }
+
+    /// Records `text` as printed now. Newlines become `\r\n`, as a terminal
+    /// in raw mode needs them.
+    pub fn write(&mut self, text: &str) -> std::io::Result<()> {
+        let text = serde_json::to_string(&text.replace("\r\n", "\n").replace('\n', "\r\n"))?;
+        writeln!(self.out, "[{:.6}, \"o\", {}]", self.start.elapsed().as_secs_f64(), text)?;
+        self.out.flush()
+    }
+}
+
+#[test]
+fn test_cast() {
+    let path = std::env::temp_dir().join(format!("cast-{}.cast", std::process::id()));
+    let mut cast = Cast::create(&path, 80, 10).unwrap();
+    cast.write("\x1b[1;1H║⠁⠂║\n").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    cast.write("epoch 1").unwrap();
+    drop(cast);
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<serde_json::Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!((lines[0]["version"].as_u64(), lines[0]["width"].as_u64(), lines[0]["height"].as_u64()), (Some(2), Some(80), Some(10)));
+    assert_eq!(lines[1][1], "o");
+    assert_eq!(lines[1][2], "\x1b[1;1H║⠁⠂║\r\n");
+    let (first, second) = (lines[1][0].as_f64().unwrap(), lines[2][0].as_f64().unwrap());
+    assert!(second - first >= 0.02);
+}
@@ -0,0 +1,33 @@
+use super::Cell;
+
+/// Wolfram's elementary automata are shown as a space-time diagram: the bottom
+/// row is the current generation and the rows above it scroll up. The row
+/// wraps around, as walls of dead cells leave stripes across chaotic rules.
+pub fn tick(cell: &mut Cell, cells: &[Cell], width: usize, i: usize, rule: u8) {
+    if i + width < cells.len() {
+        *cell = cells[i + width].clone();
+        return;
+    }
+    let (row, x) = (i - i % width, i % width);
+    let left = cells[row + (x + width - 1) % width].is_alive;
+    let right = cells[row + (x + 1) % width].is_alive;
+    let pattern = (left as u8) << 2 | (cells[i].is_alive as u8) << 1 | right as u8;
+    cell.is_alive = rule >> pattern & 1 == 1;
+    cell.get_older();
+}
+
+#[test]
+fn test_rule90() {
+    use super::{Rules, World};
+
+    let mut world = World::new(Rules::Elementary(90), 5, 3, 0, 0.0);
+    world.revive(2, 2);
+    world.tick();
+    world.tick();
+    let rows: Vec<String> = world
+        .cells
+        .chunks(5)
+        .map(|row| row.iter().map(|c| if c.is_alive { 'O' } else { '.' }).collect())
+        .collect();
+    assert_eq!(rows, ["..O..", ".O.O.", "O...O"]);
+}
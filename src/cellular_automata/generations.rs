@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+use super::{rulestring::RuleString, Cell, Neighbors};
+
+/// Generations rule `B<birth>/S<survival>/C<states>`: a live cell that does
+/// not survive goes through `states - 2` dying states before it is dead, and
+/// only live cells count as neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GenerationsRule {
+    pub birth: u32,
+    pub survival: u32,
+    pub states: u8,
+}
+
+impl GenerationsRule {
+    /// Parses `B2/S/C3` as well as the older `S/B/C` notation such as `345/2/4`.
+    pub fn parse(input: &str) -> Result<GenerationsRule, String> {
+        let unknown = || format!("Unknown Generations rule: {}", input);
+        let (counts, states) = input.rsplit_once('/').ok_or_else(unknown)?;
+        let states = states.strip_prefix(['C', 'c', 'G', 'g']).unwrap_or(states);
+        let RuleString { birth, survival } = RuleString::parse(counts).map_err(|_| unknown())?;
+        Ok(GenerationsRule {
+            birth,
+            survival,
+            states: states.parse().ok().filter(|&s| s >= 2).ok_or_else(unknown)?,
+        })
+    }
+
+    pub fn next(&self, state: u8, alive_neighbors: u8) -> u8 {
+        let rule = RuleString { birth: self.birth, survival: self.survival };
+        match state {
+            0 => rule.born(alive_neighbors) as u8,
+            1 if rule.survives(alive_neighbors) => 1,
+            state => (state + 1) % self.states,
+        }
+    }
+}
+
+impl std::fmt::Display for GenerationsRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let rule = RuleString { birth: self.birth, survival: self.survival };
+        write!(f, "{}/C{}", rule, self.states)
+    }
+}
+
+pub fn tick(cell: &mut Cell, neighbors: &Neighbors, rule: &GenerationsRule) {
+    let alive_neighbors = neighbors.iter().flatten().filter(|c| c.state == 1).count() as u8;
+    cell.state = rule.next(cell.state, alive_neighbors);
+    cell.is_alive = cell.state != 0;
+    cell.get_older();
+}
+
+#[test]
+fn test_brians_brain() {
+    let rule = GenerationsRule::parse("B2/S/C3").unwrap();
+    assert_eq!(GenerationsRule::parse("/2/3"), Ok(rule));
+    assert_eq!(rule.to_string(), "B2/S/C3");
+    assert_eq!((rule.next(0, 2), rule.next(1, 2), rule.next(2, 2)), (1, 2, 0));
+    assert_eq!(GenerationsRule::parse("345/2/4").unwrap().next(1, 4), 1);
+}
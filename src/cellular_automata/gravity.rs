@@ -1,3 +1,5 @@
+use rand::Rng;
+
 use super::{Cell, Neighbors};
 
 pub fn tick(cell: &mut Cell, neighbors: &Neighbors, stack: bool) {
@@ -18,9 +20,9 @@ pub fn tick(cell: &mut Cell, neighbors: &Neighbors, stack: bool) {
     // println!("alive({}), protected({})", cell.is_alive, cell.is_protected)
 }
 
-pub fn populate(y: usize, cell: &mut Cell, p: f32) {
+pub fn populate(y: usize, cell: &mut Cell, p: f32, rng: &mut impl Rng) {
     cell.is_alive = if y == 0 {
-        rand::random::<f32>() < p
+        rng.gen::<f32>() < p
     } else {
         cell.is_alive
     }
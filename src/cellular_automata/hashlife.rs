@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+
+use super::rulestring::RuleString;
+
+/// Deepest the tree grows, so coordinates stay within an `i64`.
+pub const MAX_LEVEL: u32 = 60;
+
+/// Largest power of two stepped at once, leaving room to pad the root.
+const MAX_STEP: u32 = MAX_LEVEL - 4;
+
+/// Nodes kept before those the root no longer reaches are dropped.
+const MAX_NODES: usize = 1 << 22;
+
+const DEAD: usize = 0;
+const ALIVE: usize = 1;
+
+/// A single cell on level 0, otherwise a `2^level` square of four children
+/// in `nw, ne, sw, se` order.
+#[derive(Debug, Clone, Copy)]
+struct Quad {
+    level: u32,
+    children: [usize; 4],
+    population: u64,
+}
+
+/// An unbounded two-state world run with Gosper's HashLife: identical
+/// quadtree nodes are shared and each node's future is worked out once, so
+/// huge regular patterns like metapixels fit in memory and run quickly.
+pub struct Universe {
+    pub rule: RuleString,
+    pub generation: u64,
+    nodes: Vec<Quad>,
+    ids: HashMap<[usize; 4], usize>,
+    /// A node's centre `2^j` generations on, by node and `j`.
+    memo: HashMap<(usize, u32), usize>,
+    /// The empty node of each level.
+    empty: Vec<usize>,
+    root: usize,
+    /// Coordinates of the root's top left cell.
+    origin: (i64, i64),
+}
+
+impl Universe {
+    /// An empty universe. Rules with B0 are refused, as every empty cell of
+    /// an unbounded world would be born.
+    pub fn new(rule: RuleString) -> Result<Universe, String> {
+        if rule.born(0) {
+            return Err(format!("{} has B0, which HashLife cannot run", rule));
+        }
+        let leaf = |population| Quad { level: 0, children: [DEAD; 4], population };
+        let mut universe = Universe {
+            rule,
+            generation: 0,
+            nodes: vec![leaf(0), leaf(1)],
+            ids: HashMap::new(),
+            memo: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            origin: (0, 0),
+        };
+        universe.root = universe.empty(3);
+        Ok(universe)
+    }
+
+    /// The node made of `children`, shared with any identical one.
+    fn join(&mut self, children: [usize; 4]) -> usize {
+        if let Some(&id) = self.ids.get(&children) {
+            return id;
+        }
+        let level = self.nodes[children[0]].level + 1;
+        let population = children.iter().map(|&child| self.nodes[child].population).fold(0, u64::saturating_add);
+        self.nodes.push(Quad { level, children, population });
+        self.ids.insert(children, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    pub fn empty(&mut self, level: u32) -> usize {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let node = self.join([below; 4]);
+            self.empty.push(node);
+        }
+        self.empty[level as usize]
+    }
+
+    /// The node of `level` with `cells` live, relative to its top left corner.
+    pub fn node(&mut self, level: u32, cells: &[(u64, u64)]) -> usize {
+        if cells.is_empty() {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return ALIVE;
+        }
+        let half = 1 << (level - 1);
+        let mut quadrants: [Vec<(u64, u64)>; 4] = Default::default();
+        for &(x, y) in cells {
+            quadrants[(x >= half) as usize + 2 * (y >= half) as usize].push((x % half, y % half));
+        }
+        let children = quadrants.map(|cells| self.node(level - 1, &cells));
+        self.join(children)
+    }
+
+    /// The node of `level` with these children, any of them `None` if empty.
+    pub fn branch(&mut self, level: u32, children: [Option<usize>; 4]) -> usize {
+        let empty = self.empty(level - 1);
+        self.join(children.map(|child| child.unwrap_or(empty)))
+    }
+
+    /// Makes `root` the whole universe, its top left cell at `origin`.
+    pub fn set_root(&mut self, root: usize, origin: (i64, i64)) {
+        self.root = root;
+        self.origin = origin;
+        while self.nodes[self.root].level < 3 {
+            self.expand();
+        }
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root].population
+    }
+
+    /// Runs `generations` generations, the largest power of two at a time.
+    pub fn step(&mut self, mut generations: u64) {
+        while generations > 0 {
+            let j = (u64::BITS - 1 - generations.leading_zeros()).min(MAX_STEP);
+            self.step_pow2(j);
+            generations -= 1 << j;
+        }
+    }
+
+    fn step_pow2(&mut self, j: u32) {
+        // The root's centre is all that is left after stepping, so the
+        // pattern needs room to spread into first.
+        while self.nodes[self.root].level < j + 3 || !self.padded() {
+            self.expand();
+        }
+        self.expand();
+        let quarter = 1i64 << (self.nodes[self.root].level - 2);
+        self.root = self.successor(self.root, j);
+        self.origin = (self.origin.0 + quarter, self.origin.1 + quarter);
+        self.generation += 1 << j;
+        if self.nodes.len() > MAX_NODES {
+            self.collect();
+        }
+    }
+
+    /// Whether every live cell is in the middle half of the root.
+    fn padded(&self) -> bool {
+        let [nw, ne, sw, se] = self.nodes[self.root].children;
+        let inner = [(nw, 3), (ne, 2), (sw, 1), (se, 0)]
+            .iter()
+            .map(|&(child, inner)| self.nodes[self.nodes[child].children[inner]].population)
+            .fold(0, u64::saturating_add);
+        inner == self.population()
+    }
+
+    /// Surrounds the root with empty space, doubling its side.
+    fn expand(&mut self) {
+        let Quad { level, children: [nw, ne, sw, se], .. } = self.nodes[self.root];
+        let e = self.empty(level - 1);
+        let children = [self.join([e, e, e, nw]), self.join([e, e, ne, e]), self.join([e, sw, e, e]), self.join([se, e, e, e])];
+        self.root = self.join(children);
+        let half = 1i64 << (level - 1);
+        self.origin = (self.origin.0 - half, self.origin.1 - half);
+    }
+
+    fn centre(&mut self, node: usize) -> usize {
+        let [nw, ne, sw, se] = self.nodes[node].children.map(|child| self.nodes[child].children);
+        self.join([nw[3], ne[2], sw[1], se[0]])
+    }
+
+    /// The centre half of `node`, `2^j` generations on, with `j` at most
+    /// the node's level less two.
+    fn successor(&mut self, node: usize, j: u32) -> usize {
+        if let Some(&next) = self.memo.get(&(node, j)) {
+            return next;
+        }
+        let Quad { level, children, population } = self.nodes[node];
+        let next = if population == 0 {
+            self.empty(level - 1)
+        } else if level == 2 {
+            self.brute(node)
+        } else {
+            let [nw, ne, sw, se] = children;
+            let [a, b, c, d] = children.map(|child| self.nodes[child].children);
+            // The nine overlapping squares half the node's side.
+            let parts = [
+                nw,
+                self.join([a[1], b[0], a[3], b[2]]),
+                ne,
+                self.join([a[2], a[3], c[0], c[1]]),
+                self.join([a[3], b[2], c[1], d[0]]),
+                self.join([b[2], b[3], d[0], d[1]]),
+                sw,
+                self.join([c[1], d[0], c[3], d[2]]),
+                se,
+            ];
+            // Either half of the steps now and half in the second round, or
+            // all of them in the second round.
+            let parts = if j == level - 2 {
+                parts.map(|part| self.successor(part, level - 3))
+            } else {
+                parts.map(|part| self.centre(part))
+            };
+            let j = j.min(level - 3);
+            let quads = [[0, 1, 3, 4], [1, 2, 4, 5], [3, 4, 6, 7], [4, 5, 7, 8]];
+            let next = quads.map(|quad| {
+                let joined = self.join(quad.map(|i| parts[i]));
+                self.successor(joined, j)
+            });
+            self.join(next)
+        };
+        self.memo.insert((node, j), next);
+        next
+    }
+
+    /// The centre 2x2 cells of a 4x4 node a generation on, counted out.
+    fn brute(&mut self, node: usize) -> usize {
+        let mut alive = [[false; 4]; 4];
+        for (i, &child) in self.nodes[node].children.iter().enumerate() {
+            for (k, &cell) in self.nodes[child].children.iter().enumerate() {
+                alive[i / 2 * 2 + k / 2][i % 2 * 2 + k % 2] = cell == ALIVE;
+            }
+        }
+        let next = [(1, 1), (2, 1), (1, 2), (2, 2)].map(|(x, y): (usize, usize)| {
+            let neighbors = (y - 1..=y + 1)
+                .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                .filter(|&(nx, ny)| (nx, ny) != (x, y) && alive[ny][nx])
+                .count();
+            if self.rule.next(alive[y][x], neighbors as u8) {
+                ALIVE
+            } else {
+                DEAD
+            }
+        });
+        self.join(next)
+    }
+
+    /// Rebuilds the tables from the root, dropping every node it does not
+    /// reach and the futures worked out so far.
+    fn collect(&mut self) {
+        let old = std::mem::take(&mut self.nodes);
+        self.nodes.extend_from_slice(&old[..2]);
+        self.ids.clear();
+        self.memo.clear();
+        self.empty = vec![DEAD];
+        let mut copied = HashMap::new();
+        self.root = self.copy(&old, self.root, &mut copied);
+    }
+
+    fn copy(&mut self, old: &[Quad], node: usize, copied: &mut HashMap<usize, usize>) -> usize {
+        if node == DEAD || node == ALIVE {
+            return node;
+        }
+        if let Some(&id) = copied.get(&node) {
+            return id;
+        }
+        let children = old[node].children.map(|child| self.copy(old, child, copied));
+        let id = self.join(children);
+        copied.insert(node, id);
+        id
+    }
+
+    /// The live cells of the `width` x `height` window whose top left
+    /// corner is at `(x, y)`.
+    pub fn window(&self, x: i64, y: i64, width: usize, height: usize) -> Vec<bool> {
+        let mut cells = vec![false; width * height];
+        self.draw(self.root, self.origin, (x, y, width as i64, height as i64), &mut cells);
+        cells
+    }
+
+    fn draw(&self, node: usize, (nx, ny): (i64, i64), window: (i64, i64, i64, i64), cells: &mut [bool]) {
+        let Quad { level, children, population } = self.nodes[node];
+        let (x, y, width, height) = window;
+        let side = 1i64 << level;
+        if population == 0 || nx >= x + width || ny >= y + height || nx + side <= x || ny + side <= y {
+            return;
+        }
+        if level == 0 {
+            cells[((ny - y) * width + nx - x) as usize] = true;
+            return;
+        }
+        let half = side / 2;
+        for (i, &child) in children.iter().enumerate() {
+            self.draw(child, (nx + half * (i % 2) as i64, ny + half * (i / 2) as i64), window, cells);
+        }
+    }
+
+    /// The universe as a macrocell, with 8x8 leaves.
+    pub fn to_macrocell(&self) -> String {
+        let mut out = format!("[M2] (rust-cellular-automatas)\n#R {}\n", self.rule);
+        if self.generation > 0 {
+            out += &format!("#G {}\n", self.generation);
+        }
+        let mut lines = Vec::new();
+        if self.write(self.root, &mut lines, &mut HashMap::new()) == 0 {
+            lines.push("$".to_string());
+        }
+        for line in lines {
+            out += &line;
+            out.push('\n');
+        }
+        out
+    }
+
+    fn write(&self, node: usize, lines: &mut Vec<String>, written: &mut HashMap<usize, usize>) -> usize {
+        let Quad { level, children, population } = self.nodes[node];
+        if population == 0 {
+            return 0;
+        }
+        if let Some(&number) = written.get(&node) {
+            return number;
+        }
+        let line = if level == 3 {
+            let mut rows: Vec<String> = (0..8)
+                .map(|y| {
+                    let row: String = self.window_of(node, y).iter().map(|&alive| if alive { '*' } else { '.' }).collect();
+                    row.trim_end_matches('.').to_string() + "$"
+                })
+                .collect();
+            while rows.last().is_some_and(|row| row == "$") {
+                rows.pop();
+            }
+            rows.concat()
+        } else {
+            let numbers = children.map(|child| self.write(child, lines, written));
+            format!("{} {} {} {} {}", level, numbers[0], numbers[1], numbers[2], numbers[3])
+        };
+        lines.push(line);
+        written.insert(node, lines.len());
+        lines.len()
+    }
+
+    /// Row `y` of an 8x8 node.
+    fn window_of(&self, node: usize, y: i64) -> Vec<bool> {
+        let mut cells = vec![false; 8];
+        self.draw(node, (0, 0), (0, y, 8, 1), &mut cells);
+        cells
+    }
+}
+
+#[test]
+fn test_hashlife() {
+    let conway = RuleString::parse("B3/S23").unwrap();
+    let mut universe = Universe::new(conway).unwrap();
+    // The R-pentomino settles at generation 1103 with 116 cells, six of
+    // them in gliders flying off.
+    let root = universe.node(3, &[(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]);
+    universe.set_root(root, (0, 0));
+    universe.step(1103);
+    assert_eq!((universe.generation, universe.population()), (1103, 116));
+
+    let mut glider = Universe::new(conway).unwrap();
+    let root = glider.node(3, &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    glider.set_root(root, (0, 0));
+    let start = glider.window(0, 0, 3, 3);
+    for _ in 0..4 {
+        glider.step(1);
+    }
+    assert_eq!(glider.window(1, 1, 3, 3), start);
+    glider.step(1 << 20);
+    assert_eq!(glider.window((1 << 18) + 1, (1 << 18) + 1, 3, 3), start);
+    assert!(glider.to_macrocell().contains("#G 1048580\n"));
+
+    assert!(Universe::new(RuleString::parse("B03/S23").unwrap()).is_err());
+}
@@ -0,0 +1,35 @@
+use super::{rulestring::RuleString, Cell, Neighbors};
+
+/// The six neighbours of a cell on an offset-row hexagonal lattice, where odd
+/// rows are shifted half a cell to the right.
+pub fn neighbors<'a>(neighbors: &Neighbors<'a>, odd_row: bool) -> [Option<&'a Cell>; 6] {
+    if odd_row {
+        [
+            neighbors.up,
+            neighbors.up_right,
+            neighbors.left,
+            neighbors.right,
+            neighbors.down,
+            neighbors.down_right,
+        ]
+    } else {
+        [
+            neighbors.up_left,
+            neighbors.up,
+            neighbors.left,
+            neighbors.right,
+            neighbors.down_left,
+            neighbors.down,
+        ]
+    }
+}
+
+pub fn tick(cell: &mut Cell, neighbors: &Neighbors, odd_row: bool, rule: &RuleString) {
+    let alive_neighbors = self::neighbors(neighbors, odd_row)
+        .iter()
+        .flatten()
+        .filter(|c| c.is_alive)
+        .count() as u8;
+    cell.is_alive = rule.next(cell.is_alive, alive_neighbors);
+    cell.get_older();
+}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::Cell;
+
+/// Ring buffer of the last generations, kept as deltas against the current
+/// cells so that only what a plain tick cannot explain is stored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    pub capacity: usize,
+    deltas: VecDeque<Vec<(usize, Cell)>>,
+}
+
+/// What a cell most likely was a generation ago: a survivor one tick younger,
+/// anything else unchanged.
+fn guess(cell: &Cell) -> Cell {
+    let mut cell = cell.clone();
+    if cell.is_alive && cell.age > 0 {
+        cell.age -= 1;
+    }
+    cell
+}
+
+impl History {
+    pub fn new(capacity: usize) -> History {
+        History {
+            capacity,
+            deltas: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, previous: &[Cell], current: &[Cell]) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.deltas.len() == self.capacity {
+            self.deltas.pop_front();
+        }
+        let delta = previous
+            .iter()
+            .zip(current)
+            .enumerate()
+            .filter(|(_, (previous, current))| guess(current) != **previous)
+            .map(|(i, (previous, _))| (i, previous.clone()))
+            .collect();
+        self.deltas.push_back(delta);
+    }
+
+    /// Whether the recorded generations fit the capacity and a world of
+    /// `cells` cells, as they may not in a snapshot edited by hand.
+    pub fn fits(&self, cells: usize) -> bool {
+        self.deltas.len() <= self.capacity && self.deltas.iter().flatten().all(|&(i, _)| i < cells)
+    }
+
+    /// Turns `cells` back into the previous generation, if one is recorded.
+    pub fn pop(&mut self, cells: &mut [Cell]) -> bool {
+        let Some(delta) = self.deltas.pop_back() else {
+            return false;
+        };
+        cells.iter_mut().for_each(|cell| *cell = guess(cell));
+        for (i, cell) in delta {
+            cells[i] = cell;
+        }
+        true
+    }
+}
+
+#[test]
+fn test_step_back() {
+    use super::{Rules, World};
+
+    let mut world = World::new(Rules::Gravity(true), 20, 20, 7, 0.3);
+    world.history = History::new(5);
+    world.populate();
+    let mut generations = vec![world.cells.clone()];
+    for _ in 0..8 {
+        world.tick();
+        generations.push(world.cells.clone());
+    }
+    generations.pop();
+    for _ in 0..5 {
+        assert!(world.step_back());
+        assert_eq!(world.cells, generations.pop().unwrap());
+    }
+    assert!(!world.step_back());
+    assert_eq!(world.epoch, 3);
+}
@@ -0,0 +1,192 @@
+use super::{pattern::Pattern, rulestring::RuleString, table::ParseError};
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T, ParseError> {
+    Err(ParseError {
+        line,
+        message: message.into(),
+    })
+}
+
+/// Reads the `#` lines both versions share: `#D` descriptions, the first one
+/// naming the pattern if it starts with `Name:`, `#N` for Conway's rule and
+/// `#R` for any other, kept as written unless it is a life-like rulestring.
+fn header(pattern: &mut Pattern, line: &str) {
+    let (tag, text) = (line.get(..2).unwrap_or(line), line.get(2..).unwrap_or_default().trim());
+    match tag {
+        "#D" | "#C" => match text.strip_prefix("Name:") {
+            Some(name) if pattern.name.is_none() => pattern.name = Some(name.trim().to_string()),
+            _ => pattern.comments.push(text.to_string()),
+        },
+        "#N" => pattern.rule = Some("B3/S23".to_string()),
+        "#R" => pattern.rule = Some(RuleString::parse(text).map_or(text.to_string(), |rule| rule.to_string())),
+        _ => (),
+    }
+}
+
+fn write_header(pattern: &Pattern, version: &str, rule: impl Fn(RuleString) -> String) -> String {
+    let mut out = format!("#Life {}\n", version);
+    if let Some(name) = &pattern.name {
+        out += &format!("#D Name: {}\n", name);
+    }
+    for comment in &pattern.comments {
+        out += &format!("#D {}\n", comment);
+    }
+    // Rules other than life-like ones are written the way `--rules` takes them.
+    if let Some(text) = &pattern.rule {
+        match RuleString::parse(text) {
+            Ok(rulestring) if rulestring.to_string() == "B3/S23" => out += "#N\n",
+            Ok(rulestring) => out += &format!("#R {}\n", rule(rulestring)),
+            Err(_) => out += &format!("#R {}\n", text),
+        }
+    }
+    out
+}
+
+/// Moves cells with signed coordinates so the top left one is at the origin.
+fn normalise(pattern: &mut Pattern, cells: Vec<(i64, i64)>, extent: Option<(i64, i64, i64, i64)>) {
+    let (x0, y0, x1, y1) = cells
+        .iter()
+        .fold(extent.unwrap_or((i64::MAX, i64::MAX, i64::MIN, i64::MIN)), |(x0, y0, x1, y1), &(x, y)| {
+            (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+        });
+    if x0 > x1 {
+        return;
+    }
+    pattern.width = (x1 - x0 + 1) as usize;
+    pattern.height = (y1 - y0 + 1) as usize;
+    pattern.cells = cells.into_iter().map(|(x, y)| ((x - x0) as usize, (y - y0) as usize, 1)).collect();
+}
+
+/// Parses Life 1.05: `#P x y` blocks of `.` and `*` rows placed relative to
+/// the centre, after the shared `#` header lines.
+pub fn parse_105(source: &str) -> Result<Pattern, ParseError> {
+    let mut pattern = Pattern::default();
+    let mut cells = Vec::new();
+    // Dead cells count towards the size, so padded patterns keep it.
+    let mut extent: Option<(i64, i64, i64, i64)> = None;
+    let (mut x0, mut y) = (0i64, 0i64);
+    for (i, line) in source.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        if line.is_empty() || line.starts_with("#Life") {
+            continue;
+        }
+        if let Some(position) = line.strip_prefix("#P") {
+            let mut coordinates = position.split_whitespace().map(str::parse::<i64>);
+            match (coordinates.next(), coordinates.next()) {
+                (Some(Ok(x)), Some(Ok(top))) => (x0, y) = (x, top),
+                _ => return error(i, format!("bad block position '{}'", position.trim())),
+            }
+            continue;
+        }
+        if line.starts_with('#') {
+            header(&mut pattern, line);
+            continue;
+        }
+        for (dx, c) in line.chars().enumerate() {
+            match c {
+                '.' => (),
+                '*' | 'O' => cells.push((x0 + dx as i64, y)),
+                _ => return error(i, format!("unexpected '{}'", c)),
+            }
+        }
+        let (left, top, right, bottom) = extent.unwrap_or((x0, y, x0, y));
+        let end = x0 + line.chars().count() as i64 - 1;
+        extent = Some((left.min(x0), top.min(y), right.max(end), bottom.max(y)));
+        y += 1;
+    }
+    normalise(&mut pattern, cells, extent);
+    Ok(pattern)
+}
+
+/// Writes `pattern` as Life 1.05 in a single block centred on the origin.
+pub fn write_105(pattern: &Pattern) -> String {
+    let mut out = write_header(pattern, "1.05", |rule| rule.survival_birth());
+    out += &format!("#P {} {}\n", -(pattern.width as i64 / 2), -(pattern.height as i64 / 2));
+    let mut rows = vec![vec!['.'; pattern.width]; pattern.height];
+    for &(x, y, _) in &pattern.cells {
+        rows[y][x] = '*';
+    }
+    for row in rows {
+        out.extend(row);
+        out.push('\n');
+    }
+    out
+}
+
+/// The `#D Size: 5x3` line `write_106` keeps the size in.
+fn size(line: &str) -> Option<(i64, i64)> {
+    let size = line.strip_prefix("#D")?.trim().strip_prefix("Size:")?;
+    let (width, height) = size.trim().split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Parses Life 1.06: one `x y` line per live cell after the `#` lines.
+pub fn parse_106(source: &str) -> Result<Pattern, ParseError> {
+    let mut pattern = Pattern::default();
+    let mut cells = Vec::new();
+    // Dead cells around the live ones, if a `#D Size:` line says so.
+    let mut extent = None;
+    for (i, line) in source.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        if let Some((width, height)) = size(line) {
+            let (x0, y0) = (-(width / 2), -(height / 2));
+            extent = Some((x0, y0, x0 + width - 1, y0 + height - 1));
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            header(&mut pattern, line);
+            continue;
+        }
+        let mut coordinates = line.split_whitespace().map(str::parse::<i64>);
+        match (coordinates.next(), coordinates.next(), coordinates.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => cells.push((x, y)),
+            _ => return error(i, format!("expected `x y`, found '{}'", line)),
+        }
+    }
+    normalise(&mut pattern, cells, extent);
+    Ok(pattern)
+}
+
+/// Writes `pattern` as Life 1.06, centred on the origin. Only the live cells
+/// are listed, so the size goes in a `#D Size:` line.
+pub fn write_106(pattern: &Pattern) -> String {
+    let mut out = write_header(pattern, "1.06", |rule| rule.to_string());
+    if pattern.width > 0 && pattern.height > 0 {
+        out += &format!("#D Size: {}x{}\n", pattern.width, pattern.height);
+    }
+    let (dx, dy) = (pattern.width as i64 / 2, pattern.height as i64 / 2);
+    let mut cells = pattern.cells.clone();
+    cells.sort_by_key(|&(x, y, _)| (y, x));
+    for (x, y, _) in cells {
+        out += &format!("{} {}\n", x as i64 - dx, y as i64 - dy);
+    }
+    out
+}
+
+#[test]
+fn test_round_trip() {
+    let glider = super::plaintext::parse("!Name: Glider\n!Rule: B36/S23\n!A comment\n.O.\n..O\nOOO").unwrap();
+    assert_eq!(parse_105(&write_105(&glider)).unwrap(), glider);
+    assert_eq!(parse_106(&write_106(&glider)).unwrap(), glider);
+    assert!(write_105(&glider).contains("#R 23/36"));
+
+    let blocks = parse_105("#Life 1.05\n#N\n#P -1 -1\n.*\n#P 2 3\n*\n").unwrap();
+    assert_eq!(blocks.rule.as_deref(), Some("B3/S23"));
+    assert_eq!((blocks.width, blocks.height), (4, 5));
+    assert_eq!(blocks.cells, vec![(1, 0, 1), (3, 4, 1)]);
+    assert!(parse_106("#Life 1.06\n0 0\n1 x\n").is_err());
+
+    // Other two-state rules and dead cells around the live ones survive too.
+    let mut padded = glider.clone();
+    padded.rule = Some("B2/S34H".to_string());
+    padded.width += 3;
+    padded.height += 2;
+    padded.cells.iter_mut().for_each(|cell| (cell.0, cell.1) = (cell.0 + 1, cell.1 + 1));
+    for source in [write_105(&padded), write_106(&padded)] {
+        assert!(source.contains("#R B2/S34H"));
+        let read = if source.starts_with("#Life 1.05") { parse_105(&source) } else { parse_106(&source) };
+        assert_eq!(read.unwrap(), padded);
+    }
+    use clap::ValueEnum;
+    let snow = super::Rules::Gravity(true);
+    let rule = parse_106(&write_106(&Pattern { rule: Some(snow.to_string()), ..padded })).unwrap().rule.unwrap();
+    assert_eq!(super::Rules::from_str(&rule, false), Ok(snow));
+}
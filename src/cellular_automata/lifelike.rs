@@ -0,0 +1,7 @@
+use super::{rulestring::RuleString, Cell, Neighbors};
+
+pub fn tick(cell: &mut Cell, neighbors: &Neighbors, rule: &RuleString) {
+    let alive_neighbors = neighbors.iter().flatten().filter(|c| c.is_alive).count() as u8;
+    cell.is_alive = rule.next(cell.is_alive, alive_neighbors);
+    cell.get_older();
+}
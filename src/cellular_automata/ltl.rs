@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use super::Cell;
+
+/// Larger than Life rule in Golly's `R<range>,C<states>,M<0|1>,S<min>..<max>,B<min>..<max>,N<M|N>`
+/// notation: births and survivals depend on the number of live cells within
+/// `range` in a Moore or von Neumann neighbourhood, counting the cell itself
+/// when `middle` is set. More than 2 states decay like Generations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LtlRule {
+    pub range: u8,
+    pub states: u8,
+    pub middle: bool,
+    pub survival: (u16, u16),
+    pub birth: (u16, u16),
+    pub moore: bool,
+}
+
+impl LtlRule {
+    pub fn parse(input: &str) -> Result<LtlRule, String> {
+        let unknown = || format!("Unknown Larger than Life rule: {}", input);
+        let bounds = |term: &str| -> Option<(u16, u16)> {
+            let (min, max) = term.split_once("..")?;
+            Some((min.parse().ok()?, max.parse().ok()?)).filter(|(min, max)| min <= max)
+        };
+        let mut rule = LtlRule {
+            range: 0,
+            states: 2,
+            middle: false,
+            survival: (0, 0),
+            birth: (0, 0),
+            moore: true,
+        };
+        let (mut range, mut survival, mut birth) = (false, false, false);
+        for term in input.split(',') {
+            let (Some(key), Some(value)) = (term.get(..1), term.get(1..)) else {
+                return Err(unknown());
+            };
+            match key {
+                "R" => {
+                    rule.range = value.parse().ok().filter(|&r| (1..=10).contains(&r)).ok_or_else(unknown)?;
+                    range = true;
+                }
+                "C" => rule.states = value.parse::<u8>().map_err(|_| unknown())?.max(2),
+                "M" => rule.middle = value == "1",
+                "S" => {
+                    rule.survival = bounds(value).ok_or_else(unknown)?;
+                    survival = true;
+                }
+                "B" => {
+                    rule.birth = bounds(value).ok_or_else(unknown)?;
+                    birth = true;
+                }
+                "N" => {
+                    rule.moore = match value {
+                        "M" => true,
+                        "N" => false,
+                        _ => return Err(unknown()),
+                    }
+                }
+                _ => return Err(unknown()),
+            }
+        }
+        if !(range && survival && birth) {
+            return Err(unknown());
+        }
+        Ok(rule)
+    }
+
+    pub fn next(&self, state: u8, alive: u16) -> u8 {
+        let within = |(min, max): (u16, u16)| min <= alive && alive <= max;
+        match state {
+            0 => within(self.birth) as u8,
+            1 if within(self.survival) => 1,
+            state => (state + 1) % self.states,
+        }
+    }
+}
+
+impl std::fmt::Display for LtlRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},N{}",
+            self.range,
+            if self.states > 2 { self.states } else { 0 },
+            self.middle as u8,
+            self.survival.0,
+            self.survival.1,
+            self.birth.0,
+            self.birth.1,
+            if self.moore { "M" } else { "N" }
+        )
+    }
+}
+
+/// Live cells within range of every cell, from per-row prefix sums so that a
+/// tick costs `range` additions per cell rather than its whole neighbourhood.
+pub fn counts(cells: &[Cell], width: usize, height: usize, rule: &LtlRule) -> Vec<u16> {
+    let range = rule.range as isize;
+    let mut rows = vec![0u16; (width + 1) * height];
+    for y in 0..height {
+        for x in 0..width {
+            rows[y * (width + 1) + x + 1] = rows[y * (width + 1) + x] + (cells[y * width + x].state == 1) as u16;
+        }
+    }
+    let row_sum = |y: isize, x0: isize, x1: isize| {
+        if y < 0 || y >= height as isize {
+            return 0;
+        }
+        let (x0, x1) = (x0.max(0) as usize, (x1 + 1).min(width as isize).max(0) as usize);
+        let row = y as usize * (width + 1);
+        rows[row + x1.max(x0)] - rows[row + x0]
+    };
+    (0..width * height)
+        .map(|i| {
+            let (x, y) = ((i % width) as isize, (i / width) as isize);
+            let alive: u16 = (-range..=range)
+                .map(|dy| {
+                    let reach = if rule.moore { range } else { range - dy.abs() };
+                    row_sum(y + dy, x - reach, x + reach)
+                })
+                .sum();
+            alive - (!rule.middle && cells[i].state == 1) as u16
+        })
+        .collect()
+}
+
+pub fn tick(cell: &mut Cell, alive: u16, rule: &LtlRule) {
+    cell.state = rule.next(cell.state, alive);
+    cell.is_alive = cell.state != 0;
+    cell.get_older();
+}
+
+#[test]
+fn test_parse() {
+    let bosco = LtlRule::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+    assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+    assert!(bosco.middle && bosco.moore && bosco.states == 2);
+    assert!(LtlRule::parse("R5,C0,M1,S34..58").is_err());
+
+    // A range 1 rule with the middle left out is Life-like: B3/S23.
+    let life = LtlRule::parse("R1,C0,M0,S2..3,B3..3,NM").unwrap();
+    let mut cells = vec![Cell::default(); 9];
+    for i in [3, 4, 5] {
+        cells[i].state = 1;
+    }
+    assert_eq!(counts(&cells, 3, 3, &life), [2, 3, 2, 1, 2, 1, 2, 3, 2]);
+}
@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+
+use super::{
+    hashlife::{self, Universe},
+    pattern::Pattern,
+    rulestring::RuleString,
+    table::ParseError,
+};
+
+/// Deepest tree read, so coordinates fit in a `u64`.
+const MAX_LEVEL: u32 = 63;
+
+/// Live cells as `(min x, min y, max x, max y)` within a node.
+type Bounds = Option<(u64, u64, u64, u64)>;
+
+#[derive(Debug, Clone)]
+enum Node {
+    /// An 8x8 leaf, or a level 1 node of a multi-state pattern, as its cells.
+    Cells { level: u32, cells: Vec<(u64, u64, u8)> },
+    /// Children in `nw, ne, sw, se` order, 0 for an empty quadrant.
+    Branch { level: u32, children: [usize; 4] },
+}
+
+impl Node {
+    fn level(&self) -> u32 {
+        match self {
+            Node::Cells { level, .. } | Node::Branch { level, .. } => *level,
+        }
+    }
+}
+
+/// A pattern in Golly's macrocell format: a quadtree whose identical nodes
+/// are shared, so it can hold patterns far bigger than a dense world.
+#[derive(Debug, Clone)]
+pub struct Macrocell {
+    pub pattern: Pattern,
+    /// Nodes numbered from 1 as in the file, 0 being the empty node.
+    nodes: Vec<Node>,
+    bounds: Vec<Bounds>,
+}
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T, ParseError> {
+    Err(ParseError {
+        line,
+        message: message.into(),
+    })
+}
+
+/// Parses a `[M2]` macrocell: `#R` rule, `#G` generation and `#C` comment
+/// lines, then one node per line, leaves as `.`/`*` rows ending in `$` and
+/// branches as `level nw ne sw se`. The last node is the root.
+pub fn parse(source: &str) -> Result<Macrocell, ParseError> {
+    let mut lines = source.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+    match lines.next() {
+        Some((_, first)) if first.starts_with("[M2]") => (),
+        _ => return error(1, "missing `[M2]` header"),
+    }
+    let mut macrocell = Macrocell {
+        pattern: Pattern::default(),
+        nodes: vec![Node::Branch { level: 0, children: [0; 4] }],
+        bounds: vec![None],
+    };
+    for (line, text) in lines {
+        if text.is_empty() {
+            continue;
+        }
+        if let Some(comment) = text.strip_prefix('#') {
+            let (tag, value) = (comment.get(..1).unwrap_or_default(), comment.get(1..).unwrap_or_default().trim());
+            let pattern = &mut macrocell.pattern;
+            match tag {
+                "R" => pattern.rule = Some(value.to_string()),
+                "G" => pattern.generation = value.parse().or_else(|_| error(line, format!("bad generation '{}'", value)))?,
+                "N" => pattern.name = Some(value.to_string()),
+                "C" | "D" => pattern.comments.push(value.to_string()),
+                _ => (),
+            }
+            continue;
+        }
+        let node = if text.starts_with(['.', '*', '$']) {
+            let (mut x, mut y, mut cells) = (0, 0, Vec::new());
+            for c in text.chars() {
+                match c {
+                    '.' => x += 1,
+                    '*' if x < 8 && y < 8 => {
+                        cells.push((x, y, 1));
+                        x += 1;
+                    }
+                    '*' => return error(line, "leaves are 8x8"),
+                    '$' => (x, y) = (0, y + 1),
+                    _ => return error(line, format!("unexpected '{}' in a leaf", c)),
+                }
+            }
+            Node::Cells { level: 3, cells }
+        } else {
+            let fields = text
+                .split_whitespace()
+                .map(str::parse::<usize>)
+                .collect::<Result<Vec<usize>, _>>()
+                .or_else(|_| error(line, format!("expected `level nw ne sw se`, found '{}'", text)))?;
+            let [level, nw, ne, sw, se] = fields[..] else {
+                return error(line, format!("expected `level nw ne sw se`, found '{}'", text));
+            };
+            let level = level as u32;
+            if level == 1 {
+                let cells = [(0, 0, nw), (1, 0, ne), (0, 1, sw), (1, 1, se)]
+                    .into_iter()
+                    .filter(|&(_, _, state)| state != 0)
+                    .map(|(x, y, state)| u8::try_from(state).map(|state| (x, y, state)))
+                    .collect::<Result<Vec<_>, _>>()
+                    .or_else(|_| error(line, "states go up to 255"))?;
+                Node::Cells { level, cells }
+            } else if level == 0 || level > MAX_LEVEL {
+                return error(line, format!("level {} is not between 1 and {}", level, MAX_LEVEL));
+            } else {
+                for child in [nw, ne, sw, se] {
+                    match macrocell.nodes.get(child) {
+                        None => return error(line, format!("node {} is not defined yet", child)),
+                        Some(node) if child != 0 && node.level() != level - 1 => {
+                            return error(line, format!("node {} is not on level {}", child, level - 1))
+                        }
+                        _ => (),
+                    }
+                }
+                Node::Branch { level, children: [nw, ne, sw, se] }
+            }
+        };
+        macrocell.bounds.push(macrocell.node_bounds(&node));
+        macrocell.nodes.push(node);
+    }
+
+    if let Some((x0, y0, x1, y1)) = macrocell.bounds[macrocell.root()] {
+        macrocell.pattern.width = usize::try_from(x1 - x0 + 1).unwrap_or(usize::MAX);
+        macrocell.pattern.height = usize::try_from(y1 - y0 + 1).unwrap_or(usize::MAX);
+    }
+    Ok(macrocell)
+}
+
+impl Macrocell {
+    fn root(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    fn node_bounds(&self, node: &Node) -> Bounds {
+        match node {
+            Node::Cells { cells, .. } => cells.iter().fold(None, |bounds, &(x, y, _)| {
+                let (x0, y0, x1, y1) = bounds.unwrap_or((x, y, x, y));
+                Some((x0.min(x), y0.min(y), x1.max(x), y1.max(y)))
+            }),
+            Node::Branch { level, children } => {
+                let half = 1u64 << (level - 1);
+                let offsets = [(0, 0), (half, 0), (0, half), (half, half)];
+                children.iter().zip(offsets).fold(None, |bounds, (&child, (dx, dy))| {
+                    let Some((cx0, cy0, cx1, cy1)) = self.bounds[child] else { return bounds };
+                    let (x0, y0, x1, y1) = bounds.unwrap_or((u64::MAX, u64::MAX, 0, 0));
+                    Some((x0.min(cx0 + dx), y0.min(cy0 + dy), x1.max(cx1 + dx), y1.max(cy1 + dy)))
+                })
+            }
+        }
+    }
+
+    /// Expands the tree into its live cells, failing before it does if their
+    /// bounding box does not fit a `width` x `height` world.
+    pub fn to_pattern(&self, width: usize, height: usize) -> Result<Pattern, String> {
+        let mut pattern = self.pattern.clone();
+        let Some((x0, y0, x1, y1)) = self.bounds[self.root()] else {
+            return Ok(pattern);
+        };
+        let (pattern_width, pattern_height) = (x1 as u128 - x0 as u128 + 1, y1 as u128 - y0 as u128 + 1);
+        if pattern_width > width as u128 || pattern_height > height as u128 {
+            return Err(format!(
+                "the macrocell pattern is {}x{} cells, too big for the {}x{} world",
+                pattern_width, pattern_height, width, height
+            ));
+        }
+        self.expand(self.root(), 0, 0, &mut |x, y, state| {
+            pattern.cells.push(((x - x0) as usize, (y - y0) as usize, state))
+        });
+        Ok(pattern)
+    }
+
+    /// Loads the tree into an unbounded HashLife universe as it is, shared
+    /// nodes and all, with the top left live cell at the origin.
+    pub fn to_universe(&self, rule: RuleString) -> Result<Universe, String> {
+        let mut universe = Universe::new(rule)?;
+        universe.generation = self.pattern.generation;
+        let Some((x0, y0, _, _)) = self.bounds[self.root()] else {
+            return Ok(universe);
+        };
+        if self.nodes[self.root()].level() > hashlife::MAX_LEVEL {
+            return Err(format!("HashLife runs macrocells of up to level {}", hashlife::MAX_LEVEL));
+        }
+        // The universe's number for each node, `None` for the empty one.
+        let mut ids = vec![None; self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate().skip(1) {
+            ids[i] = Some(match node {
+                Node::Cells { level, cells } => {
+                    if cells.iter().any(|&(_, _, state)| state > 1) {
+                        return Err("HashLife runs two-state patterns only".to_string());
+                    }
+                    let cells: Vec<(u64, u64)> = cells.iter().map(|&(x, y, _)| (x, y)).collect();
+                    universe.node(*level, &cells)
+                }
+                Node::Branch { level, children } => universe.branch(*level, children.map(|child| ids[child])),
+            });
+        }
+        universe.set_root(ids[self.root()].unwrap(), (-(x0 as i64), -(y0 as i64)));
+        Ok(universe)
+    }
+
+    fn expand(&self, index: usize, x: u64, y: u64, cell: &mut impl FnMut(u64, u64, u8)) {
+        if self.bounds[index].is_none() {
+            return;
+        }
+        match &self.nodes[index] {
+            Node::Cells { cells, .. } => {
+                for &(dx, dy, state) in cells {
+                    cell(x + dx, y + dy, state);
+                }
+            }
+            Node::Branch { level, children } => {
+                let half = 1u64 << (level - 1);
+                let offsets = [(0, 0), (half, 0), (0, half), (half, half)];
+                for (&child, (dx, dy)) in children.iter().zip(offsets) {
+                    self.expand(child, x + dx, y + dy, cell);
+                }
+            }
+        }
+    }
+}
+
+/// Writes `pattern` as a macrocell, with 8x8 leaves for two-state patterns
+/// and level 1 nodes holding the states otherwise.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = "[M2] (rust-cellular-automatas)\n".to_string();
+    if let Some(rule) = &pattern.rule {
+        out += &format!("#R {}\n", rule);
+    }
+    if pattern.generation > 0 {
+        out += &format!("#G {}\n", pattern.generation);
+    }
+    if let Some(name) = &pattern.name {
+        out += &format!("#N {}\n", name);
+    }
+    for comment in &pattern.comments {
+        out += &format!("#C {}\n", comment);
+    }
+
+    let multistate = pattern.cells.iter().any(|&(_, _, state)| state > 1);
+    let leaf_level = if multistate { 1 } else { 3 };
+    let side = pattern.width.max(pattern.height).max(1);
+    let level = (usize::BITS - (side - 1).leading_zeros()).max(leaf_level);
+    let cells = pattern.cells.iter().map(|&(x, y, state)| (x as u64, y as u64, state)).collect();
+    let mut writer = Writer {
+        lines: Vec::new(),
+        ids: HashMap::new(),
+        leaf_level,
+    };
+    // An empty pattern still needs a root.
+    if writer.node(level, cells) == 0 {
+        writer.line(if multistate { "1 0 0 0 0".to_string() } else { "$".to_string() });
+    }
+    for line in writer.lines {
+        out += &line;
+        out.push('\n');
+    }
+    out
+}
+
+/// Numbers the nodes while writing, sharing identical ones.
+struct Writer {
+    lines: Vec<String>,
+    ids: HashMap<String, usize>,
+    leaf_level: u32,
+}
+
+impl Writer {
+    fn line(&mut self, line: String) -> usize {
+        if let Some(&id) = self.ids.get(&line) {
+            return id;
+        }
+        self.lines.push(line.clone());
+        self.ids.insert(line, self.lines.len());
+        self.lines.len()
+    }
+
+    /// Writes the node of the given level holding `cells`, relative to its
+    /// top left corner, and returns its number.
+    fn node(&mut self, level: u32, cells: Vec<(u64, u64, u8)>) -> usize {
+        if cells.is_empty() {
+            return 0;
+        }
+        if level == self.leaf_level && level == 1 {
+            let mut states = [0; 4];
+            for (x, y, state) in cells {
+                states[(y * 2 + x) as usize] = state;
+            }
+            return self.line(format!("1 {} {} {} {}", states[0], states[1], states[2], states[3]));
+        }
+        if level == self.leaf_level {
+            let mut rows = vec![vec!['.'; 8]; 8];
+            for (x, y, _) in cells {
+                rows[y as usize][x as usize] = '*';
+            }
+            let height = rows.iter().rposition(|row| row.contains(&'*')).map_or(0, |y| y + 1);
+            let leaf = rows[..height]
+                .iter()
+                .map(|row| row.iter().collect::<String>().trim_end_matches('.').to_string() + "$")
+                .collect();
+            return self.line(leaf);
+        }
+        let half = 1u64 << (level - 1);
+        let mut quadrants: [Vec<(u64, u64, u8)>; 4] = Default::default();
+        for (x, y, state) in cells {
+            let quadrant = (x >= half) as usize + 2 * (y >= half) as usize;
+            quadrants[quadrant].push((x % half, y % half, state));
+        }
+        let children = quadrants.map(|cells| self.node(level - 1, cells));
+        self.line(format!("{} {} {} {} {}", level, children[0], children[1], children[2], children[3]))
+    }
+}
+
+#[test]
+fn test_macrocell() {
+    let mut glider = Pattern {
+        rule: Some("B3/S23".to_string()),
+        generation: 12,
+        width: 40,
+        height: 3,
+        cells: vec![(1, 0, 1), (2, 1, 1), (0, 2, 1), (1, 2, 1), (2, 2, 1), (39, 2, 1)],
+        ..Pattern::default()
+    };
+    let source = write(&glider);
+    assert!(source.starts_with("[M2]") && source.contains("\n.*$..*$***$\n"));
+    let mut read = parse(&source).unwrap().to_pattern(40, 3).unwrap();
+    read.cells.sort_by_key(|&(x, y, _)| (y, x));
+    glider.cells.sort_by_key(|&(x, y, _)| (y, x));
+    assert_eq!(read, glider);
+
+    let states = Pattern { width: 3, height: 1, cells: vec![(0, 0, 2), (2, 0, 7)], ..Pattern::default() };
+    assert_eq!(parse(&write(&states)).unwrap().to_pattern(3, 1).unwrap(), states);
+
+    // A block in each corner of a 2^40 square.
+    let mut huge = "[M2]\n**$**$\n".to_string();
+    for level in 4..=40 {
+        huge += &format!("{} {} 0 0 {}\n", level, level - 3, level - 3);
+    }
+    let huge = parse(&huge).unwrap();
+    assert_eq!(huge.pattern.width, (1 << 40) - 6);
+    assert!(huge.to_pattern(1 << 20, 1 << 20).unwrap_err().contains("too big"));
+    // HashLife holds it as it is: 2^37 blocks, all still there later on.
+    let mut universe = huge.to_universe(RuleString::parse("B3/S23").unwrap()).unwrap();
+    universe.step(1 << 30);
+    assert_eq!(universe.population(), 1 << 39);
+    assert_eq!(universe.window((1 << 40) - 8, (1 << 40) - 8, 2, 2), [true; 4]);
+    assert!(parse(&source).unwrap().to_pattern(39, 3).is_err());
+    assert!(parse("[M2]\n4 1 0 0 0\n").is_err());
+    assert!(parse("[M2]\n.*$\n5 1 0 0 0\n").is_err());
+    assert!(parse("[M2]\n0 0 0 0 0\n").is_err());
+}
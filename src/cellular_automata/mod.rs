@@ -1,6 +1,33 @@
 mod conway;
+mod elementary;
+pub mod generations;
 mod gravity;
+pub mod hashlife;
+mod hexagonal;
+pub mod history;
 mod highlife;
+mod life;
+mod lifelike;
+pub mod ltl;
+pub mod macrocell;
+pub mod objects;
+pub mod pattern;
+mod plaintext;
+mod rle;
+pub mod snapshot;
+pub mod rulestring;
+pub mod stagnation;
+pub mod stats;
+pub mod species;
+pub mod table;
+pub mod triangular;
+pub mod world3d;
+
+use std::sync::Arc;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::canvas::{Canvas, Pixel};
 
@@ -8,11 +35,14 @@ use crate::canvas::{Canvas, Pixel};
 type Age = u64;
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Cell {
     pub is_alive: bool,
     pub is_protected: bool,
     pub age: Age,
+    pub species: u8,
+    /// State of the cell under multi-state rules, 0 being dead.
+    pub state: u8,
 }
 
 impl Cell {
@@ -21,6 +51,8 @@ impl Cell {
             is_alive: false,
             is_protected: true,
             age: 0,
+            species: 0,
+            state: 0,
         }
     }
 
@@ -46,14 +78,118 @@ pub struct Neighbors<'a> {
     up_left: Option<&'a Cell>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl<'a> Neighbors<'a> {
+    pub fn iter(&self) -> impl Iterator<Item = Option<&'a Cell>> {
+        [
+            self.up_left,
+            self.up,
+            self.up_right,
+            self.left,
+            self.right,
+            self.down_left,
+            self.down,
+            self.down_right,
+        ]
+        .into_iter()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Rules {
     Conway,
     HighLife,
     Gravity(bool),
+    Immigration,
+    QuadLife,
+    Table(Arc<table::RuleTable>),
+    Hexagonal(rulestring::RuleString),
+    Triangular(triangular::TriangularRule),
+    /// Any birth/survival rule on the square lattice.
+    LifeLike(rulestring::RuleString),
+    /// One of Wolfram's 256 one-dimensional rules.
+    Elementary(u8),
+    Generations(generations::GenerationsRule),
+    LargerThanLife(ltl::LtlRule),
+}
+
+/// How cells are laid out and which of them are neighbours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lattice {
+    Square,
+    /// Offset rows: odd rows are shifted half a cell to the right.
+    Hexagonal,
+    /// Alternating up- and down-pointing triangles, see `triangular::points_up`.
+    Triangular,
+}
+
+impl Rules {
+    /// Number of competing species the rule is defined with.
+    pub fn species(&self) -> u8 {
+        match self {
+            Rules::Immigration => 2,
+            Rules::QuadLife => 4,
+            _ => 1,
+        }
+    }
+
+    pub fn lattice(&self) -> Lattice {
+        match self {
+            Rules::Hexagonal(_) => Lattice::Hexagonal,
+            Rules::Triangular(_) => Lattice::Triangular,
+            _ => Lattice::Square,
+        }
+    }
+
+    /// Whether the rule is a birth/survival rule, so live cells can carry a species.
+    pub fn is_life_like(&self) -> bool {
+        !matches!(
+            self,
+            Rules::Gravity(_) | Rules::Table(_) | Rules::Elementary(_) | Rules::Generations(_) | Rules::LargerThanLife(_)
+        )
+    }
+
+    /// Number of cell states, dead included, for the rules that have more than two.
+    pub fn states(&self) -> u16 {
+        match self {
+            Rules::Table(table) => table.n_states,
+            Rules::Generations(rule) => rule.states as u16,
+            Rules::LargerThanLife(rule) => rule.states as u16,
+            _ => 2,
+        }
+    }
+
+    /// Birth/survival counts of the two-state rules on the square lattice;
+    /// species only colour the cells, so Immigration and QuadLife are Conway's.
+    pub fn rulestring(&self) -> Option<rulestring::RuleString> {
+        match self {
+            Rules::Conway | Rules::Immigration | Rules::QuadLife => Some(rulestring::RuleString { birth: 1 << 3, survival: 1 << 2 | 1 << 3 }),
+            Rules::HighLife => Some(rulestring::RuleString { birth: 1 << 3 | 1 << 6, survival: 1 << 2 | 1 << 3 }),
+            Rules::LifeLike(rule) => Some(*rule),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug)]
+/// Written the way `--rules` accepts it.
+impl std::fmt::Display for Rules {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Rules::Conway | Rules::HighLife | Rules::LifeLike(_) => write!(f, "{}", self.rulestring().unwrap()),
+            Rules::Gravity(true) => write!(f, "snow"),
+            Rules::Gravity(false) => write!(f, "rain"),
+            Rules::Immigration => write!(f, "immigration"),
+            Rules::QuadLife => write!(f, "quadlife"),
+            Rules::Table(table) => write!(f, "{}", table.name),
+            Rules::Hexagonal(rule) => write!(f, "{}H", rule),
+            Rules::Triangular(rule) => write!(f, "{}T", rule),
+            Rules::Elementary(rule) => write!(f, "W{}", rule),
+            Rules::Generations(rule) => write!(f, "{}", rule),
+            Rules::LargerThanLife(rule) => write!(f, "{}", rule),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct World {
     pub width: usize,
     pub height: usize,
@@ -62,6 +198,13 @@ pub struct World {
     pub epoch: u64,
     pub reset_at_epoch: u64,
     pub pop_rate: f32,
+    pub species: u8,
+    pub history: history::History,
+    pub detector: stagnation::Detector,
+    pub stats_history: stats::StatsHistory,
+    /// Source of every random choice, seed it to reproduce a run. The
+    /// generator behind `StdRng`, used directly so snapshots can save it.
+    pub rng: ChaCha12Rng,
 }
 
 impl World {
@@ -75,9 +218,14 @@ impl World {
         World {
             width,
             height,
-            rule,
+            rule: rule.clone(),
             reset_at_epoch,
             pop_rate,
+            species: rule.species(),
+            history: history::History::default(),
+            detector: stagnation::Detector::default(),
+            stats_history: stats::StatsHistory::default(),
+            rng: ChaCha12Rng::from_entropy(),
             epoch: 0,
             cells: (0..(width * height))
                 .into_iter()
@@ -87,14 +235,21 @@ impl World {
     }
 
     pub fn tick(&mut self) {
+        let previous = (self.history.capacity > 0).then(|| self.cells.clone());
         self.epoch += 1;
 
-        if self.reset_at_epoch > 0 && self.epoch % self.reset_at_epoch == 0 {
-            self.cells = self.cells.iter().map(|_| Cell::default()).collect();
-            self.populate();
+        if (self.reset_at_epoch > 0 && self.epoch.is_multiple_of(self.reset_at_epoch))
+            || self.detector.should_reset(self.epoch)
+        {
+            self.reset();
         }
 
         let mut new_cells = self.cells.clone();
+        let mut census = stats::Census::default();
+        let ltl_counts = match &self.rule {
+            Rules::LargerThanLife(rule) => ltl::counts(&self.cells, self.width, self.height, rule),
+            _ => Vec::new(),
+        };
         self.cells.iter().enumerate().for_each(|(i, _)| {
             let neighbors = Neighbors {
                 up: if i >= self.width {
@@ -140,40 +295,126 @@ impl World {
                     None
                 },
             };
-            match self.rule {
-                Rules::Conway => conway::tick(&mut new_cells[i], &neighbors),
+            let was_alive = new_cells[i].is_alive;
+            let odd_row = (i / self.width) % 2 == 1;
+            match &self.rule {
+                Rules::Conway | Rules::Immigration | Rules::QuadLife => {
+                    conway::tick(&mut new_cells[i], &neighbors)
+                }
                 Rules::HighLife => highlife::tick(&mut new_cells[i], &neighbors),
-                Rules::Gravity(stack) => gravity::tick(&mut new_cells[i], &neighbors, stack),
+                Rules::LifeLike(rule) => lifelike::tick(&mut new_cells[i], &neighbors, rule),
+                Rules::Elementary(rule) => elementary::tick(&mut new_cells[i], &self.cells, self.width, i, *rule),
+                Rules::Generations(rule) => generations::tick(&mut new_cells[i], &neighbors, rule),
+                Rules::LargerThanLife(rule) => ltl::tick(&mut new_cells[i], ltl_counts[i], rule),
+                Rules::Hexagonal(rule) => {
+                    hexagonal::tick(&mut new_cells[i], &neighbors, odd_row, rule)
+                }
+                Rules::Triangular(rule) => {
+                    let (edge, vertex) = triangular::neighbors(&self.cells, self.width, self.height, i);
+                    triangular::tick(&mut new_cells[i], &edge, &vertex, rule)
+                }
+                Rules::Gravity(stack) => {
+                    gravity::tick(&mut new_cells[i], &neighbors, *stack);
+                    gravity::populate(i / self.width, &mut new_cells[i], self.pop_rate * 0.1, &mut self.rng);
+                }
+                Rules::Table(table) => {
+                    let cell = &mut new_cells[i];
+                    let mut states = [0; 9];
+                    cell.state = table.next(table.neighbor_states(cell.state, &neighbors, &mut states));
+                    cell.is_alive = cell.state != 0;
+                    cell.get_older();
+                }
             };
+            if self.species > 1 && !was_alive && new_cells[i].is_alive {
+                let parents = match self.rule.lattice() {
+                    Lattice::Hexagonal => hexagonal::neighbors(&neighbors, odd_row).to_vec(),
+                    Lattice::Triangular => {
+                        let (edge, vertex) = triangular::neighbors(&self.cells, self.width, self.height, i);
+                        edge.into_iter().chain(vertex).map(Some).collect()
+                    }
+                    Lattice::Square => neighbors.iter().collect(),
+                };
+                new_cells[i].species = species::inherit(&parents, self.species);
+            }
+            census.count(i % self.width, i / self.width, &self.cells[i], &new_cells[i]);
         });
         self.cells = new_cells;
-        match self.rule {
-            Rules::Gravity(_) => {
-                let old_pop_rate = self.pop_rate;
-                self.pop_rate = self.pop_rate * 0.1;
-                self.populate();
-                self.pop_rate = old_pop_rate;
-            }
-            _ => (),
+        self.stats_history.push(census.finish(self.epoch, self.cells.len()));
+        if let Some(previous) = previous {
+            self.history.push(&previous, &self.cells);
+        }
+        self.detector.observe(&self.cells, self.epoch);
+    }
+
+    /// Makes the following random choices, and so the run, reproducible.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+    }
+
+    pub fn reset(&mut self) {
+        self.cells = self.cells.iter().map(|_| Cell::default()).collect();
+        self.detector.clear();
+        self.populate();
+    }
+
+    /// Census of the last generation, gathered while ticking.
+    pub fn stats(&self) -> stats::Stats {
+        self.stats_history.last().copied().unwrap_or_default()
+    }
+
+    /// Whether the world died out or settled into a cycle, as of the last tick.
+    pub fn stagnation(&self) -> Option<stagnation::Stagnation> {
+        self.detector.stagnation()
+    }
+
+    /// Period of the cycle the world is in, 1 for still lifes.
+    pub fn period(&self) -> Option<usize> {
+        match self.stagnation() {
+            Some(stagnation::Stagnation::Periodic(period)) => Some(period),
+            _ => None,
+        }
+    }
+
+    /// Goes back one generation, returns false once the history is exhausted.
+    pub fn step_back(&mut self) -> bool {
+        if !self.history.pop(&mut self.cells) {
+            return false;
         }
+        self.epoch -= 1;
+        self.detector.clear();
+        true
     }
 
-    #[allow(dead_code)]
     pub fn kill(&mut self, x: usize, y: usize) {
         self.cells[y * self.width + x].is_alive = false;
+        self.cells[y * self.width + x].state = 0;
     }
 
     pub fn revive(&mut self, x: usize, y: usize) {
         self.cells[y * self.width + x].is_alive = true;
+        self.cells[y * self.width + x].state = 1;
     }
 
     pub fn populate(&mut self) {
         for (index, cell) in self.cells.iter_mut().enumerate() {
             let y = index / self.width;
-            match self.rule {
-                Rules::Gravity(_) => gravity::populate(y, cell, self.pop_rate),
-                _ => cell.is_alive = rand::random::<f32>() < self.pop_rate,
+            match &self.rule {
+                Rules::Gravity(_) => gravity::populate(y, cell, self.pop_rate, &mut self.rng),
+                Rules::Table(table) => {
+                    cell.is_alive = self.rng.gen::<f32>() < self.pop_rate;
+                    cell.state = if cell.is_alive {
+                        1 + (self.rng.gen::<u16>() % (table.n_states - 1)) as u8
+                    } else {
+                        0
+                    };
+                }
+                Rules::Elementary(_) => cell.is_alive = y + 1 == self.height && self.rng.gen::<f32>() < self.pop_rate,
+                _ => {
+                    cell.is_alive = self.rng.gen::<f32>() < self.pop_rate;
+                    cell.state = cell.is_alive as u8;
+                }
             }
+            species::populate(cell, self.species, &mut self.rng);
         }
     }
 }
@@ -186,22 +427,31 @@ impl std::fmt::Display for World {
 
 impl From<&World> for Canvas {
     fn from(world: &World) -> Canvas {
-        let mut canvas = Canvas::new(world.width as usize, world.height as usize);
+        // Hexagonal cells are two dots wide so odd rows can be staggered by one dot.
+        let (scale, stagger) = match world.rule.lattice() {
+            Lattice::Hexagonal => (2, 1),
+            Lattice::Square | Lattice::Triangular => (1, 0),
+        };
+        let mut canvas = Canvas::new(world.width * scale + stagger, world.height);
+        canvas.colored = world.species > 1 || world.rule.states() > 2;
         for (i, cell) in world.cells.iter().enumerate() {
             let x = i % world.width as usize;
             let y = i / world.width as usize;
             // println!("xy({},{}) -> {}", x, y, cell.is_alive);
             if cell.is_alive {
-                canvas.draw_pixel(
-                    x,
-                    y,
-                    Pixel {
-                        r: 255,
-                        g: 255,
-                        b: 255,
-                        a: 255,
-                    },
-                );
+                let [r, g, b] = match &world.rule {
+                    Rules::Table(table) => table.color(cell.state).unwrap_or([255, 255, 255]),
+                    _ if world.species > 1 => species::color(cell.species),
+                    // Dying cells fade out.
+                    rule if rule.states() > 2 => {
+                        let level = 255 - ((cell.state - 1) as usize * 191 / (rule.states() - 1) as usize) as u8;
+                        [level, level, level]
+                    }
+                    _ => [255, 255, 255],
+                };
+                for dx in 0..scale {
+                    canvas.draw_pixel(x * scale + dx + stagger * (y % 2), y, Pixel { r, g, b, a: 255 });
+                }
             }
         }
         canvas
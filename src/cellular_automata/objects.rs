@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::{rulestring::RuleString, World};
+
+/// Longest period looked for when identifying an object.
+const MAX_PERIOD: usize = 64;
+
+type Point = (i64, i64);
+
+/// Common names of the objects soups usually settle into, by apgcode.
+const CATALOGUE: &[(&str, &str)] = &[
+    ("xs4_33", "block"),
+    ("xs6_696", "beehive"),
+    ("xs7_2596", "loaf"),
+    ("xs5_253", "boat"),
+    ("xs6_356", "ship"),
+    ("xs4_252", "tub"),
+    ("xs8_6996", "pond"),
+    ("xs6_25a4", "barge"),
+    ("xs7_25ac", "long boat"),
+    ("xs6_bd", "snake"),
+    ("xs8_69ic", "mango"),
+    ("xp2_7", "blinker"),
+    ("xp2_7e", "toad"),
+    ("xp2_318c", "beacon"),
+    ("xp3_co9nas0san9oczgoldlo0oldlogz1047210127401", "pulsar"),
+    ("xp15_4r4z4r4", "pentadecathlon"),
+    ("xq4_153", "glider"),
+    ("xq4_6frc", "lightweight spaceship"),
+    ("xq4_27dee6", "middleweight spaceship"),
+    ("xq4_27deee6", "heavyweight spaceship"),
+];
+
+/// Common name of the object with the given apgcode, if it has one.
+pub fn name(apgcode: &str) -> Option<&'static str> {
+    CATALOGUE.iter().find(|(code, _)| *code == apgcode).map(|(_, name)| *name)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kind {
+    StillLife,
+    Oscillator { period: usize },
+    Spaceship { period: usize, dx: i64, dy: i64 },
+    /// Did not repeat itself within `MAX_PERIOD` generations.
+    Aperiodic,
+}
+
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub apgcode: String,
+    pub name: Option<&'static str>,
+    pub kind: Kind,
+    pub population: usize,
+    /// Live cells of the object in world coordinates.
+    pub cells: Vec<(usize, usize)>,
+}
+
+impl Object {
+    pub fn description(&self) -> String {
+        let kind = match &self.kind {
+            Kind::StillLife => "still life".to_string(),
+            Kind::Oscillator { period } => format!("p{} oscillator", period),
+            Kind::Spaceship { period, dx, dy } => {
+                let direction = if *dx == 0 || *dy == 0 { "orthogonal" } else { "diagonal" };
+                format!("{}c/{} {} spaceship", dx.abs().max(dy.abs()), period, direction)
+            }
+            Kind::Aperiodic => "aperiodic".to_string(),
+        };
+        match self.name {
+            Some(name) => format!("{} ({})", name, kind),
+            None => format!("{} cells {}", self.population, kind),
+        }
+    }
+}
+
+/// Splits the live cells of a settled world into objects and identifies them.
+/// Connected cells form an object; one that does not repeat on its own is
+/// merged with its neighbours within two cells, as the pulsar's quarters are.
+pub fn analyse(world: &World) -> Result<Vec<Object>, String> {
+    let rule = world
+        .rule
+        .rulestring()
+        .ok_or_else(|| "object recognition needs a life-like rule".to_string())?;
+    let mut objects: Vec<Object> = components(world)
+        .into_iter()
+        .map(|cells| identify(cells, &rule))
+        .collect();
+    while let Some((i, j)) = (0..objects.len())
+        .filter(|&i| objects[i].kind == Kind::Aperiodic)
+        .find_map(|i| (0..objects.len()).find(|&j| j != i && near(&objects[i], &objects[j])).map(|j| (i, j)))
+    {
+        let (first, second) = (objects.swap_remove(i.max(j)), objects.swap_remove(i.min(j)));
+        objects.push(identify([first.cells, second.cells].concat(), &rule));
+    }
+    Ok(objects)
+}
+
+/// Identifies just the connected groups of live cells that `select` picks,
+/// each on its own, without merging any with their neighbours.
+pub fn analyse_some(world: &World, select: impl Fn(&[(usize, usize)]) -> bool) -> Result<Vec<Object>, String> {
+    let rule = world
+        .rule
+        .rulestring()
+        .ok_or_else(|| "object recognition needs a life-like rule".to_string())?;
+    Ok(components(world)
+        .into_iter()
+        .filter(|cells| select(cells))
+        .map(|cells| identify(cells, &rule))
+        .collect())
+}
+
+fn near(a: &Object, b: &Object) -> bool {
+    a.cells
+        .iter()
+        .any(|&(ax, ay)| b.cells.iter().any(|&(bx, by)| ax.abs_diff(bx) <= 2 && ay.abs_diff(by) <= 2))
+}
+
+/// Groups of live cells touching each other, diagonally included.
+fn components(world: &World) -> Vec<Vec<(usize, usize)>> {
+    let alive = |x: isize, y: isize| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < world.width
+            && (y as usize) < world.height
+            && world.cells[y as usize * world.width + x as usize].is_alive
+    };
+    let mut seen = vec![false; world.cells.len()];
+    let mut components = Vec::new();
+    for start in 0..world.cells.len() {
+        if seen[start] || !world.cells[start].is_alive {
+            continue;
+        }
+        seen[start] = true;
+        let mut stack = vec![(start % world.width, start / world.width)];
+        let mut component = Vec::new();
+        while let Some((x, y)) = stack.pop() {
+            component.push((x, y));
+            for dy in -1..=1isize {
+                for dx in -1..=1isize {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    if alive(nx, ny) {
+                        let i = ny as usize * world.width + nx as usize;
+                        if !seen[i] {
+                            seen[i] = true;
+                            stack.push((nx as usize, ny as usize));
+                        }
+                    }
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+fn identify(cells: Vec<(usize, usize)>, rule: &RuleString) -> Object {
+    let start: HashSet<Point> = cells.iter().map(|&(x, y)| (x as i64, y as i64)).collect();
+    let mut phases = vec![start.clone()];
+    let mut kind = Kind::Aperiodic;
+    for period in 1..=MAX_PERIOD {
+        let next = step(phases.last().unwrap(), rule);
+        if let Some((dx, dy)) = translation(&start, &next) {
+            kind = match (period, dx, dy) {
+                (1, 0, 0) => Kind::StillLife,
+                (_, 0, 0) => Kind::Oscillator { period },
+                _ => Kind::Spaceship { period, dx, dy },
+            };
+            break;
+        }
+        if next.is_empty() {
+            break;
+        }
+        phases.push(next);
+    }
+
+    let apgcode = match kind {
+        Kind::StillLife => format!("xs{}_{}", start.len(), canonical(&phases)),
+        Kind::Oscillator { period } => format!("xp{}_{}", period, canonical(&phases)),
+        Kind::Spaceship { period, .. } => format!("xq{}_{}", period, canonical(&phases)),
+        Kind::Aperiodic => format!("zz_{}", canonical(&phases[..1])),
+    };
+    Object {
+        name: name(&apgcode),
+        apgcode,
+        kind,
+        population: cells.len(),
+        cells,
+    }
+}
+
+fn step(cells: &HashSet<Point>, rule: &RuleString) -> HashSet<Point> {
+    let mut counts: HashMap<Point, u8> = HashMap::new();
+    for &(x, y) in cells {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx != 0 || dy != 0 {
+                    *counts.entry((x + dx, y + dy)).or_default() += 1;
+                }
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .filter(|(point, count)| rule.next(cells.contains(point), *count))
+        .map(|(point, _)| point)
+        .collect()
+}
+
+fn corner(cells: &HashSet<Point>) -> Point {
+    (
+        cells.iter().map(|p| p.0).min().unwrap_or(0),
+        cells.iter().map(|p| p.1).min().unwrap_or(0),
+    )
+}
+
+/// How far `b` is from `a`, if it is the same shape.
+fn translation(a: &HashSet<Point>, b: &HashSet<Point>) -> Option<Point> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    let ((ax, ay), (bx, by)) = (corner(a), corner(b));
+    let (dx, dy) = (bx - ax, by - ay);
+    a.iter().all(|&(x, y)| b.contains(&(x + dx, y + dy))).then_some((dx, dy))
+}
+
+/// Shortest, then alphabetically first, extended Wechsler encoding over every
+/// phase and orientation, as apgsearch does.
+fn canonical(phases: &[HashSet<Point>]) -> String {
+    let orientations: [fn(Point) -> Point; 8] = [
+        |(x, y)| (x, y),
+        |(x, y)| (-x, y),
+        |(x, y)| (x, -y),
+        |(x, y)| (-x, -y),
+        |(x, y)| (y, x),
+        |(x, y)| (-y, x),
+        |(x, y)| (y, -x),
+        |(x, y)| (-y, -x),
+    ];
+    phases
+        .iter()
+        .flat_map(|phase| {
+            orientations
+                .iter()
+                .map(move |orient| wechsler(&phase.iter().map(|&p| orient(p)).collect()))
+        })
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_default()
+}
+
+fn wechsler(cells: &HashSet<Point>) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let (x0, y0) = corner(cells);
+    let width = cells.iter().map(|p| p.0 - x0 + 1).max().unwrap_or(0);
+    let height = cells.iter().map(|p| p.1 - y0 + 1).max().unwrap_or(0);
+
+    let mut strips = Vec::new();
+    for strip in 0..(height + 4) / 5 {
+        let mut columns: Vec<u8> = (0..width)
+            .map(|x| {
+                (0..5)
+                    .filter(|row| cells.contains(&(x0 + x, y0 + strip * 5 + row)))
+                    .map(|row| 1 << row)
+                    .sum()
+            })
+            .collect();
+        while columns.last() == Some(&0) {
+            columns.pop();
+        }
+
+        let mut encoded = String::new();
+        let mut zeros = 0;
+        let flush = |encoded: &mut String, zeros: &mut usize| {
+            while *zeros > 0 {
+                let run = (*zeros).min(39);
+                match run {
+                    1 => encoded.push('0'),
+                    2 => encoded.push('w'),
+                    3 => encoded.push('x'),
+                    _ => {
+                        encoded.push('y');
+                        encoded.push(DIGITS[run - 4] as char);
+                    }
+                }
+                *zeros -= run;
+            }
+        };
+        for column in columns {
+            if column == 0 {
+                zeros += 1;
+            } else {
+                flush(&mut encoded, &mut zeros);
+                encoded.push(DIGITS[column as usize] as char);
+            }
+        }
+        strips.push(encoded);
+    }
+    strips.join("z")
+}
+
+/// Number of each object found, by apgcode, with the first one seen.
+#[derive(Debug, Default)]
+pub struct ObjectCensus {
+    pub counts: BTreeMap<String, usize>,
+    pub examples: BTreeMap<String, Object>,
+}
+
+impl ObjectCensus {
+    pub fn add(&mut self, objects: &[Object]) {
+        for object in objects {
+            *self.counts.entry(object.apgcode.clone()).or_default() += 1;
+            self.examples.entry(object.apgcode.clone()).or_insert_with(|| object.clone());
+        }
+    }
+
+    pub fn merge(&mut self, other: ObjectCensus) {
+        for (apgcode, count) in other.counts {
+            *self.counts.entry(apgcode).or_default() += count;
+        }
+        for (apgcode, object) in other.examples {
+            self.examples.entry(apgcode).or_insert(object);
+        }
+    }
+
+    /// Objects from the most to the least common.
+    pub fn sorted(&self) -> Vec<(&String, usize)> {
+        let mut counts: Vec<(&String, usize)> = self.counts.iter().map(|(code, &n)| (code, n)).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        counts
+    }
+
+    /// The `n` most common objects, e.g. `12 block, 5 blinker, 1 xs7_2596`.
+    pub fn summary(&self, n: usize) -> String {
+        self.sorted()
+            .into_iter()
+            .take(n)
+            .map(|(apgcode, count)| format!("{} {}", count, name(apgcode).unwrap_or(apgcode)))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+impl std::fmt::Display for ObjectCensus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        for (apgcode, count) in self.sorted() {
+            writeln!(f, "{:<40} {:>8} {}", apgcode, count, self.examples[apgcode].description())?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_identify() {
+    use super::Rules;
+
+    let patterns = [
+        (&["OO", "OO"][..], "block"),
+        (&[".OO.", "O..O", ".OO."][..], "beehive"),
+        (&["OOO"][..], "blinker"),
+        (&[".OOO", "OOO."][..], "toad"),
+        (&["OO..", "OO..", "..OO", "..OO"][..], "beacon"),
+        (&[".O.", "..O", "OOO"][..], "glider"),
+        (&[".O..O", "O....", "O...O", "OOOO."][..], "lightweight spaceship"),
+        (&["..OOO...OOO..", ".............", "O....O.O....O", "O....O.O....O", "O....O.O....O", "..OOO...OOO..", ".............", "..OOO...OOO..", "O....O.O....O", "O....O.O....O", "O....O.O....O", ".............", "..OOO...OOO.."][..], "pulsar"),
+    ];
+    for (rows, name) in patterns {
+        let mut world = World::new(Rules::Conway, 20, 20, 0, 0.0);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == 'O' {
+                    world.revive(x + 3, y + 3);
+                }
+            }
+        }
+        let objects = analyse(&world).unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].name, Some(name), "{} identified as {}", name, objects[0].apgcode);
+    }
+
+    // The bi-block is two blocks side by side, not an object of its own.
+    let mut world = World::new(Rules::Conway, 20, 20, 0, 0.0);
+    for (x, y) in [(3, 3), (4, 3), (3, 4), (4, 4), (6, 3), (7, 3), (6, 4), (7, 4)] {
+        world.revive(x, y);
+    }
+    let mut census = ObjectCensus::default();
+    census.add(&analyse(&world).unwrap());
+    assert_eq!(census.summary(3), "2 block");
+}
@@ -0,0 +1,169 @@
+use std::path::Path;
+
+use super::{life, macrocell, plaintext, rle, World};
+
+/// Pattern file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Rle,
+    /// `.cells`, rows of `.` and `O`.
+    Plaintext,
+    Life105,
+    Life106,
+    /// Golly's `.mc` quadtree.
+    Macrocell,
+}
+
+impl Format {
+    /// The format a file name asks for, Life 1.06 for `.lif`.
+    pub fn from_extension(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "rle" => Some(Format::Rle),
+            "cells" => Some(Format::Plaintext),
+            "lif" | "life" => Some(Format::Life106),
+            "mc" => Some(Format::Macrocell),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the `#Life` header, then the extension, then
+    /// what the first lines look like.
+    pub fn detect(path: &Path, source: &str) -> Format {
+        let first = source.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+        let has_blocks = source.lines().any(|line| line.trim_start().starts_with("#P"));
+        if first.starts_with("#Life 1.05") {
+            return Format::Life105;
+        }
+        if first.starts_with("#Life 1.06") {
+            return Format::Life106;
+        }
+        if first.starts_with("[M2]") {
+            return Format::Macrocell;
+        }
+        match Format::from_extension(path) {
+            Some(Format::Life106) if has_blocks => Format::Life105,
+            Some(format) => format,
+            None if first.starts_with('!') || first.chars().all(|c| matches!(c, '.' | 'O' | '*')) => Format::Plaintext,
+            None if has_blocks => Format::Life105,
+            None => Format::Rle,
+        }
+    }
+
+    /// Reads a pattern, failing for macrocells bigger than a `width` x
+    /// `height` world before they are expanded.
+    pub fn parse(self, source: &str, width: usize, height: usize) -> Result<Pattern, String> {
+        let parsed = match self {
+            Format::Rle => rle::parse(source),
+            Format::Plaintext => plaintext::parse(source),
+            Format::Life105 => life::parse_105(source),
+            Format::Life106 => life::parse_106(source),
+            Format::Macrocell => return macrocell::parse(source).map_err(|e| e.to_string())?.to_pattern(width, height),
+        };
+        parsed.map_err(|e| e.to_string())
+    }
+
+    pub fn write(self, pattern: &Pattern) -> String {
+        match self {
+            Format::Rle => rle::write(pattern),
+            Format::Plaintext => plaintext::write(pattern),
+            Format::Life105 => life::write_105(pattern),
+            Format::Life106 => life::write_106(pattern),
+            Format::Macrocell => macrocell::write(pattern),
+        }
+    }
+}
+
+/// A pattern read from or written to a file, independent of any world.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pattern {
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+    /// Rule as written in the file, e.g. `B3/S23`.
+    pub rule: Option<String>,
+    /// Generation the pattern was saved at.
+    pub generation: u64,
+    pub width: usize,
+    pub height: usize,
+    /// Live cells as `(x, y, state)` from the top left corner, state 1 in
+    /// two-state patterns.
+    pub cells: Vec<(usize, usize, u8)>,
+}
+
+impl Pattern {
+    /// Reads a pattern in any of the formats, detected by [`Format::detect`],
+    /// for a `width` x `height` world.
+    pub fn load(path: &Path, width: usize, height: usize) -> Result<Pattern, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Format::detect(path, &source)
+            .parse(&source, width, height)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+impl World {
+    /// The live cells within their bounding box, with the rule and generation.
+    pub fn to_pattern(&self) -> Pattern {
+        let multistate = self.rule.states() > 2;
+        let live: Vec<(usize, usize, u8)> = self
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.is_alive)
+            .map(|(i, cell)| (i % self.width, i / self.width, if multistate { cell.state } else { 1 }))
+            .collect();
+        let x0 = live.iter().map(|c| c.0).min().unwrap_or(0);
+        let y0 = live.iter().map(|c| c.1).min().unwrap_or(0);
+        let cells: Vec<(usize, usize, u8)> = live.into_iter().map(|(x, y, state)| (x - x0, y - y0, state)).collect();
+        Pattern {
+            rule: Some(self.rule.to_string()),
+            generation: self.epoch,
+            width: cells.iter().map(|c| c.0 + 1).max().unwrap_or(0),
+            height: cells.iter().map(|c| c.1 + 1).max().unwrap_or(0),
+            cells,
+            ..Pattern::default()
+        }
+    }
+
+    pub fn to_rle(&self) -> String {
+        rle::write(&self.to_pattern())
+    }
+
+    /// Writes the live cells to `path` in the format its extension names,
+    /// RLE if it names none.
+    pub fn save_pattern(&self, path: &Path) -> std::io::Result<()> {
+        let source = match Format::from_extension(path) {
+            Some(format) => format.write(&self.to_pattern()),
+            None => self.to_rle(),
+        };
+        std::fs::write(path, source)
+    }
+
+    /// Writes `pattern` into the world with its top left corner at `(x, y)`.
+    pub fn stamp(&mut self, pattern: &Pattern, x: usize, y: usize) -> Result<(), String> {
+        if x + pattern.width > self.width || y + pattern.height > self.height {
+            return Err(format!(
+                "a {}x{} pattern at {},{} does not fit in a {}x{} world",
+                pattern.width, pattern.height, x, y, self.width, self.height
+            ));
+        }
+        for &(dx, dy, state) in &pattern.cells {
+            let cell = &mut self.cells[(y + dy) * self.width + x + dx];
+            cell.state = state;
+            cell.is_alive = state != 0;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_detect() {
+    let path = Path::new("pattern.txt");
+    assert_eq!(Format::detect(path, "#N Glider\nx = 3, y = 3\nbo$2bo$3o!"), Format::Rle);
+    assert_eq!(Format::detect(path, "!Name: Glider\n.O.\n..O\nOOO"), Format::Plaintext);
+    assert_eq!(Format::detect(path, ".O.\n..O\nOOO"), Format::Plaintext);
+    assert_eq!(Format::detect(path, "#Life 1.05\n#P 0 0\n*"), Format::Life105);
+    assert_eq!(Format::detect(path, "#Life 1.06\n0 0"), Format::Life106);
+    assert_eq!(Format::detect(Path::new("glider.lif"), "#D Glider\n#P 0 0\n*"), Format::Life105);
+    assert_eq!(Format::detect(Path::new("glider.cells"), "OO\nOO"), Format::Plaintext);
+    assert_eq!(Format::detect(path, "[M2] (golly 4.2)\n#R B3/S23\n.*$"), Format::Macrocell);
+}
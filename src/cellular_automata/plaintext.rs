@@ -0,0 +1,76 @@
+use super::{pattern::Pattern, table::ParseError};
+
+/// Parses a plaintext `.cells` pattern: `!` comment lines, `!Name:` and
+/// `!Rule:` among them, then rows of `.` (dead) and `O` or `*` (alive).
+pub fn parse(source: &str) -> Result<Pattern, ParseError> {
+    let mut pattern = Pattern::default();
+    let mut rows = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('!') {
+            let comment = comment.trim();
+            if let Some(name) = comment.strip_prefix("Name:") {
+                pattern.name = Some(name.trim().to_string());
+            } else if let Some(rule) = comment.strip_prefix("Rule:") {
+                pattern.rule = Some(rule.trim().to_string());
+            } else {
+                pattern.comments.push(comment.to_string());
+            }
+            continue;
+        }
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => (),
+                'O' | '*' => pattern.cells.push((x, rows.len(), 1)),
+                _ => {
+                    return Err(ParseError {
+                        line: i + 1,
+                        message: format!("unexpected '{}'", c),
+                    })
+                }
+            }
+        }
+        rows.push(line.chars().count());
+    }
+    while rows.last() == Some(&0) {
+        rows.pop();
+    }
+    pattern.width = rows.iter().copied().max().unwrap_or(0);
+    pattern.height = rows.len();
+    Ok(pattern)
+}
+
+/// Writes `pattern` as plaintext, every row padded to the full width so the
+/// size survives. Multi-state cells all become `O`.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out += &format!("!Name: {}\n", name);
+    }
+    if let Some(rule) = &pattern.rule {
+        out += &format!("!Rule: {}\n", rule);
+    }
+    for comment in &pattern.comments {
+        out += &format!("!{}\n", comment);
+    }
+    let mut rows = vec![vec!['.'; pattern.width]; pattern.height];
+    for &(x, y, _) in &pattern.cells {
+        rows[y][x] = 'O';
+    }
+    for row in rows {
+        out.extend(row);
+        out.push('\n');
+    }
+    out
+}
+
+#[test]
+fn test_round_trip() {
+    let glider = parse("!Name: Glider\n!A comment\n.O.\n..*\nOOO\n...\n").unwrap();
+    assert_eq!(glider.name.as_deref(), Some("Glider"));
+    assert_eq!(glider.comments, vec!["A comment"]);
+    assert_eq!((glider.width, glider.height), (3, 4));
+    assert_eq!(glider.cells, vec![(1, 0, 1), (2, 1, 1), (0, 2, 1), (1, 2, 1), (2, 2, 1)]);
+    assert_eq!(parse(&write(&glider)).unwrap(), glider);
+    assert!(parse("!Name: bad\n.O.\n.x.").is_err());
+}
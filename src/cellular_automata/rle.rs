@@ -0,0 +1,242 @@
+use super::{pattern::Pattern, table::ParseError};
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T, ParseError> {
+    Err(ParseError {
+        line,
+        message: message.into(),
+    })
+}
+
+/// Parses a Run Length Encoded pattern: `#` comment lines, the
+/// `x = 3, y = 3, rule = B3/S23` header, then runs of `b` (dead), `o` (alive)
+/// or the multi-state `A`-`X` and `pA`-`yO` states, with `$` ending rows and
+/// `!` the pattern.
+pub fn parse(source: &str) -> Result<Pattern, ParseError> {
+    let mut pattern = Pattern::default();
+    let mut lines = source.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+    let (header_line, header) = loop {
+        match lines.next() {
+            Some((_, "")) => continue,
+            Some((_, line)) if line.starts_with('#') => {
+                let (tag, text) = (line.get(..2).unwrap_or(line), line.get(2..).unwrap_or_default());
+                match tag {
+                    "#N" => pattern.name = Some(text.trim().to_string()),
+                    "#r" => pattern.rule = Some(text.trim().to_string()),
+                    // Golly's extended RLE keeps the generation in a comment.
+                    "#C" if text.starts_with("XRLE") => {
+                        if let Some(generation) = text.split_whitespace().find_map(|field| field.strip_prefix("Gen=")) {
+                            pattern.generation = generation.parse().unwrap_or_default();
+                        }
+                    }
+                    "#C" | "#c" | "#O" => pattern.comments.push(text.trim().to_string()),
+                    _ => (),
+                }
+            }
+            Some(header) => break header,
+            None => return error(0, "missing `x = , y = ` header"),
+        }
+    };
+    // The rule runs to the end of the line, as Larger than Life rules have
+    // commas of their own.
+    let (header, rule) = match header.split_once("rule") {
+        Some((fields, rule)) => match rule.trim_start().strip_prefix('=') {
+            Some(rule) => (fields.trim_end().trim_end_matches(','), Some(rule.trim())),
+            None => return error(header_line, "expected `rule = ` in the header"),
+        },
+        None => (header, None),
+    };
+    if let Some(rule) = rule {
+        pattern.rule = Some(rule.to_string());
+    }
+    for field in header.split(',') {
+        let Some((key, value)) = field.split_once('=') else {
+            return error(header_line, format!("expected `key = value`, found '{}'", field.trim()));
+        };
+        let value = value.trim();
+        match key.trim() {
+            "x" => pattern.width = value.parse().or_else(|_| error(header_line, format!("bad width '{}'", value)))?,
+            "y" => pattern.height = value.parse().or_else(|_| error(header_line, format!("bad height '{}'", value)))?,
+            _ => (),
+        }
+    }
+
+    let (mut x, mut y) = (0usize, 0usize);
+    'lines: for (line, text) in lines {
+        let mut chars = text.chars().peekable();
+        let mut count = 0usize;
+        while let Some(c) = chars.next() {
+            let run = count.max(1);
+            match c {
+                '0'..='9' => {
+                    count = match count.checked_mul(10).and_then(|n| n.checked_add(c.to_digit(10).unwrap() as usize)) {
+                        Some(count) => count,
+                        None => return error(line, "run count is too large"),
+                    };
+                    continue;
+                }
+                'b' | '.' => x = x.saturating_add(run),
+                '$' => (x, y) = (0, y.saturating_add(run)),
+                '!' => break 'lines,
+                c if c.is_whitespace() => continue,
+                c => {
+                    let state = match c {
+                        'o' => 1,
+                        'A'..='X' => c as u8 - b'A' + 1,
+                        'p'..='y' => match chars.next() {
+                            Some(low @ 'A'..='X') => {
+                                let state = 24 * (c as usize - 'p' as usize + 1) + (low as usize - 'A' as usize + 1);
+                                if state > 255 {
+                                    return error(line, format!("state {} is out of range", state));
+                                }
+                                state as u8
+                            }
+                            _ => return error(line, format!("'{}' must be followed by a state letter", c)),
+                        },
+                        _ => return error(line, format!("unexpected '{}'", c)),
+                    };
+                    if y >= pattern.height || run > pattern.width.saturating_sub(x) {
+                        let message = format!("run of {} at {},{} is outside the {}x{} header", run, x, y, pattern.width, pattern.height);
+                        return error(line, message);
+                    }
+                    for dx in 0..run {
+                        pattern.cells.push((x + dx, y, state));
+                    }
+                    x += run;
+                }
+            }
+            count = 0;
+        }
+    }
+    Ok(pattern)
+}
+
+/// Longest line of an RLE file.
+const LINE_LENGTH: usize = 70;
+
+fn tag(state: u8, multistate: bool) -> String {
+    match (state, multistate) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (1..=24, true) => ((b'A' + state - 1) as char).to_string(),
+        _ => {
+            let (high, low) = ((state - 25) / 24, (state - 25) % 24);
+            format!("{}{}", (b'p' + high) as char, (b'A' + low) as char)
+        }
+    }
+}
+
+/// Writes `pattern` as RLE, with its rule and generation in the header and
+/// lines wrapped at 70 characters.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out += &format!("#N {}\n", name);
+    }
+    for comment in &pattern.comments {
+        out += &format!("#C {}\n", comment);
+    }
+    if pattern.generation > 0 {
+        out += &format!("#CXRLE Pos=0,0 Gen={}\n", pattern.generation);
+    }
+    out += &format!("x = {}, y = {}", pattern.width, pattern.height);
+    if let Some(rule) = &pattern.rule {
+        out += &format!(", rule = {}", rule);
+    }
+    out += "\n";
+
+    let multistate = pattern.cells.iter().any(|&(_, _, state)| state > 1);
+    let mut rows = vec![Vec::new(); pattern.height];
+    for &(x, y, state) in &pattern.cells {
+        rows[y].push((x, state));
+    }
+    // Runs of (count, tag), dead cells at the end of rows left out.
+    let mut runs: Vec<(usize, String)> = Vec::new();
+    let push = |runs: &mut Vec<(usize, String)>, count: usize, tag: String| match runs.last_mut() {
+        Some((previous, last)) if *last == tag => *previous += count,
+        _ => runs.push((count, tag)),
+    };
+    for (y, row) in rows.iter_mut().enumerate() {
+        if y > 0 {
+            push(&mut runs, 1, "$".to_string());
+        }
+        row.sort_unstable();
+        let mut x = 0;
+        for &(cell_x, state) in row.iter() {
+            if cell_x > x {
+                push(&mut runs, cell_x - x, tag(0, multistate));
+            }
+            push(&mut runs, 1, tag(state, multistate));
+            x = cell_x + 1;
+        }
+    }
+    while runs.last().is_some_and(|(_, tag)| tag == "$") {
+        runs.pop();
+    }
+    runs.push((1, "!".to_string()));
+
+    let mut line = String::new();
+    for (count, tag) in runs {
+        let run = if count > 1 { format!("{}{}", count, tag) } else { tag };
+        if line.len() + run.len() > LINE_LENGTH {
+            out += &line;
+            out += "\n";
+            line.clear();
+        }
+        line += &run;
+    }
+    out += &line;
+    out += "\n";
+    out
+}
+
+#[test]
+fn test_parse() {
+    let glider = parse("#N Glider\n#C The smallest spaceship.\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n").unwrap();
+    assert_eq!(glider.name.as_deref(), Some("Glider"));
+    assert_eq!(glider.comments, ["The smallest spaceship."]);
+    assert_eq!(glider.rule.as_deref(), Some("B3/S23"));
+    assert_eq!((glider.width, glider.height), (3, 3));
+    assert_eq!(glider.cells, [(1, 0, 1), (2, 1, 1), (0, 2, 1), (1, 2, 1), (2, 2, 1)]);
+
+    let multistate = parse("x = 4, y = 3, rule = B2/S/C3\n2A$.BpA$\nA!").unwrap();
+    assert_eq!(multistate.cells, [(0, 0, 1), (1, 0, 1), (1, 1, 2), (2, 1, 25), (0, 2, 1)]);
+
+    assert_eq!(parse("x = 1, y = 1\nz!").unwrap_err().line, 2);
+    // Runs past the header are refused before their cells are made.
+    assert!(parse("x = 3, y = 1\n4o!").is_err());
+    assert!(parse("x = 3, y = 1\n$o!").is_err());
+    assert!(parse("x = 3, y = 1\n99999999999999999999999o!").is_err());
+
+    // Golly writes Larger than Life rules, commas and all, at the end.
+    let bugs = parse("x = 2, y = 1, rule = R5,C0,M1,S34..58,B34..45,NM\n2o!").unwrap();
+    assert_eq!(bugs.rule.as_deref(), Some("R5,C0,M1,S34..58,B34..45,NM"));
+    assert_eq!((bugs.width, bugs.height), (2, 1));
+}
+
+#[test]
+fn test_write() {
+    let glider = parse("x = 3, y = 3, rule = B3/S23\nbob$2bo$3o!").unwrap();
+    assert_eq!(write(&glider), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+
+    let mut wide = Pattern { width: 200, height: 3, generation: 7, ..Pattern::default() };
+    wide.cells = (0..100).map(|x| (x * 2, x % 3, 1 + x as u8)).collect();
+    let rle = write(&wide);
+    assert!(rle.lines().all(|line| line.len() <= LINE_LENGTH));
+    let read = parse(&rle).unwrap();
+    assert_eq!((read.generation, read.width, read.height), (7, 200, 3));
+    let mut cells = read.cells;
+    cells.sort_by_key(|&(x, y, _)| (y, x));
+    wide.cells.sort_by_key(|&(x, y, _)| (y, x));
+    assert_eq!(cells, wide.cells);
+
+    // A Larger than Life world reads back with its rule.
+    use super::{ltl::LtlRule, Rules, World};
+    let rule = Rules::LargerThanLife(LtlRule::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap());
+    let mut world = World::new(rule.clone(), 20, 20, 0, 0.3);
+    world.populate();
+    let read = parse(&world.to_rle()).unwrap();
+    assert_eq!(read.rule, Some(rule.to_string()));
+    assert_eq!(read.cells.len(), world.cells.iter().filter(|cell| cell.is_alive).count());
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// Outer-totalistic birth/survival rule, stored as bitmasks indexed by the
+/// number of live neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RuleString {
+    pub birth: u32,
+    pub survival: u32,
+}
+
+impl RuleString {
+    /// Parses `B3/S23` as well as the older `23/3` survival/birth notation.
+    pub fn parse(input: &str) -> Result<RuleString, String> {
+        let unknown = || format!("Unknown rulestring: {}", input);
+        let (first, second) = input.split_once('/').ok_or_else(unknown)?;
+        let (birth, survival) = match (first.strip_prefix(['B', 'b']), second.strip_prefix(['S', 's'])) {
+            (Some(birth), Some(survival)) => (birth, survival),
+            _ => match (first.strip_prefix(['S', 's']), second.strip_prefix(['B', 'b'])) {
+                (Some(survival), Some(birth)) => (birth, survival),
+                _ => (second, first),
+            },
+        };
+        Ok(RuleString {
+            birth: counts(birth).ok_or_else(unknown)?,
+            survival: counts(survival).ok_or_else(unknown)?,
+        })
+    }
+
+    /// A random rule without B0. With B0 every empty cell is born at once, so
+    /// the whole world strobes on and off.
+    pub fn random(rng: &mut impl rand::Rng) -> RuleString {
+        RuleString {
+            birth: rng.gen_range(0..1 << 8) << 1,
+            survival: rng.gen_range(0..1 << 9),
+        }
+    }
+
+    pub fn born(&self, alive_neighbors: u8) -> bool {
+        self.birth & (1 << alive_neighbors) != 0
+    }
+
+    pub fn survives(&self, alive_neighbors: u8) -> bool {
+        self.survival & (1 << alive_neighbors) != 0
+    }
+
+    /// The older survival/birth notation, `23/3` for Conway's Life.
+    pub fn survival_birth(&self) -> String {
+        format!("{}/{}", digits(self.survival), digits(self.birth))
+    }
+
+    pub fn next(&self, is_alive: bool, alive_neighbors: u8) -> bool {
+        if is_alive {
+            self.survives(alive_neighbors)
+        } else {
+            self.born(alive_neighbors)
+        }
+    }
+}
+
+fn counts(digits: &str) -> Option<u32> {
+    digits
+        .chars()
+        .map(|c| c.to_digit(10))
+        .try_fold(0, |mask, d| d.map(|d| mask | 1 << d))
+}
+
+fn digits(mask: u32) -> String {
+    (0..=9)
+        .filter(|d| mask & (1 << d) != 0)
+        .map(|d| char::from_digit(d, 10).unwrap())
+        .collect()
+}
+
+impl std::fmt::Display for RuleString {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "B{}/S{}", digits(self.birth), digits(self.survival))
+    }
+}
+
+#[test]
+fn test_parse() {
+    let conway = RuleString::parse("B3/S23").unwrap();
+    assert_eq!(RuleString::parse("23/3"), Ok(conway));
+    assert_eq!(RuleString::parse("s23/b3"), Ok(conway));
+    assert_eq!(RuleString::parse("B33/S223"), Ok(conway));
+    assert!(conway.born(3) && conway.survives(2) && !conway.born(2));
+    assert_eq!(conway.to_string(), "B3/S23");
+    assert!(RuleString::parse("B3x/S23").is_err());
+}
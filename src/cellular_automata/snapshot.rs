@@ -0,0 +1,206 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    generations::GenerationsRule, ltl::LtlRule, rulestring::RuleString, species::MAX_SPECIES, triangular::TriangularRule,
+    Rules, World,
+};
+
+/// Version of the snapshot layout, bumped whenever `World` changes shape.
+pub const VERSION: u32 = 2;
+
+/// First bytes of a binary snapshot, followed by the version.
+const MAGIC: &[u8; 4] = b"CAWS";
+
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    version: u32,
+    world: &'a World,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct Loaded {
+    world: World,
+}
+
+fn check(version: u32) -> Result<(), String> {
+    if version != VERSION {
+        return Err(format!(
+            "snapshot version {} is not supported, this build reads version {}",
+            version, VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the rule is one its own parser could have given, read back from
+/// its text where it has one.
+fn is_valid(rule: &Rules) -> bool {
+    match rule {
+        Rules::Table(table) => table.is_consistent(),
+        Rules::Hexagonal(rule) | Rules::LifeLike(rule) => RuleString::parse(&rule.to_string()) == Ok(*rule),
+        Rules::Triangular(rule) => TriangularRule::parse(&rule.to_string()) == Ok(*rule),
+        Rules::Generations(rule) => GenerationsRule::parse(&rule.to_string()) == Ok(*rule),
+        Rules::LargerThanLife(rule) => LtlRule::parse(&rule.to_string()) == Ok(*rule),
+        _ => true,
+    }
+}
+
+/// Rejects a world whose parts disagree on its size, rule or states, which
+/// would panic later.
+fn validate(world: World) -> Result<World, String> {
+    if world.width.checked_mul(world.height) != Some(world.cells.len()) {
+        return Err(format!(
+            "corrupt snapshot: {} cells for a {}x{} world",
+            world.cells.len(),
+            world.width,
+            world.height
+        ));
+    }
+    if !world.history.fits(world.cells.len()) {
+        return Err("corrupt snapshot: the history does not fit the world".to_string());
+    }
+    if !world.detector.is_consistent() {
+        return Err("corrupt snapshot: the stagnation detector remembers more than its longest period".to_string());
+    }
+    if !(1..=MAX_SPECIES).contains(&world.species) {
+        return Err(format!("corrupt snapshot: {} species, expected 1 to {}", world.species, MAX_SPECIES));
+    }
+    if !is_valid(&world.rule) {
+        return Err(format!("corrupt snapshot: invalid parameters for rule {}", world.rule));
+    }
+    let states = world.rule.states();
+    if states > 2 && world.cells.iter().any(|cell| cell.is_alive != (cell.state != 0) || cell.state as u16 >= states) {
+        return Err(format!("corrupt snapshot: cells outside the {} states of the rule", states));
+    }
+    Ok(world)
+}
+
+impl World {
+    /// The whole world, rule, history and random generator included, as
+    /// compact binary.
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(bincode::serialize(self).expect("worlds always serialise"));
+        bytes
+    }
+
+    /// The whole world as JSON, for reading or editing by hand.
+    pub fn to_snapshot_json(&self) -> String {
+        serde_json::to_string(&Snapshot { version: VERSION, world: self }).expect("worlds always serialise")
+    }
+
+    /// Reads a binary or JSON snapshot, rejecting other versions.
+    pub fn from_snapshot(bytes: &[u8]) -> Result<World, String> {
+        if let Some(rest) = bytes.strip_prefix(MAGIC) {
+            let version = rest.get(..4).ok_or("truncated snapshot")?;
+            check(u32::from_le_bytes(version.try_into().unwrap()))?;
+            let world = bincode::deserialize(&rest[4..]).map_err(|e| format!("corrupt snapshot: {}", e))?;
+            return validate(world);
+        }
+        let header: Header = serde_json::from_slice(bytes).map_err(|e| format!("not a snapshot: {}", e))?;
+        check(header.version)?;
+        let loaded: Loaded = serde_json::from_slice(bytes).map_err(|e| format!("corrupt snapshot: {}", e))?;
+        validate(loaded.world)
+    }
+
+    /// Writes a snapshot to `path`, JSON for `.json` and binary otherwise.
+    pub fn save_snapshot(&self, path: &Path) -> std::io::Result<()> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => std::fs::write(path, self.to_snapshot_json()),
+            _ => std::fs::write(path, self.to_snapshot()),
+        }
+    }
+
+    pub fn load_snapshot(path: &Path) -> Result<World, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        World::from_snapshot(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[test]
+fn test_snapshot() {
+    use super::{generations::GenerationsRule, history::History, Rules};
+
+    let run = || {
+        let mut world = World::new(Rules::Generations(GenerationsRule::parse("B2/S/C5").unwrap()), 24, 16, 0, 0.4);
+        world.seed(3);
+        world.history = History::new(8);
+        world.populate();
+        for _ in 0..10 {
+            world.tick();
+        }
+        world
+    };
+    for snapshot in [run().to_snapshot(), run().to_snapshot_json().into_bytes()] {
+        // Same cells, history and random generator: both carry on identically.
+        let (mut world, mut resumed) = (run(), World::from_snapshot(&snapshot).unwrap());
+        assert_eq!(resumed.rule, world.rule);
+        for world in [&mut world, &mut resumed] {
+            world.tick();
+            world.populate();
+            world.tick();
+        }
+        assert_eq!((resumed.epoch, &resumed.cells), (world.epoch, &world.cells));
+        assert!(resumed.step_back());
+    }
+
+    let mut old = run().to_snapshot();
+    old[4] = 9;
+    assert!(World::from_snapshot(&old).unwrap_err().contains("version 9"));
+    let json = run().to_snapshot_json().replacen(&format!("\"version\":{}", VERSION), "\"version\":0", 1);
+    assert!(World::from_snapshot(json.as_bytes()).unwrap_err().contains("version 0"));
+    assert!(World::from_snapshot(b"CAWS").is_err());
+
+    let mut wrong = run();
+    wrong.width += 1;
+    assert!(World::from_snapshot(&wrong.to_snapshot()).unwrap_err().contains("corrupt snapshot: 384 cells"));
+    let mut wrong = run();
+    wrong.history.capacity = 2;
+    assert!(World::from_snapshot(wrong.to_snapshot_json().as_bytes()).unwrap_err().contains("history"));
+    for species in [0, MAX_SPECIES + 1] {
+        let mut wrong = run();
+        wrong.species = species;
+        assert!(World::from_snapshot(&wrong.to_snapshot()).unwrap_err().contains("species"));
+    }
+    for states in [0, 1] {
+        let mut wrong = run();
+        wrong.rule = Rules::Generations(GenerationsRule { states, ..GenerationsRule::parse("B2/S/C5").unwrap() });
+        assert!(World::from_snapshot(&wrong.to_snapshot()).unwrap_err().contains("invalid parameters"));
+        let mut wrong = run();
+        wrong.rule = Rules::LargerThanLife(LtlRule { states, ..LtlRule::parse("R2,C0,M0,S3..5,B3..4,NM").unwrap() });
+        assert!(World::from_snapshot(&wrong.to_snapshot()).unwrap_err().contains("invalid parameters"));
+    }
+    let mut wrong = run();
+    wrong.rule = Rules::LifeLike(RuleString { birth: 1 << 12, survival: 0 });
+    assert!(World::from_snapshot(&wrong.to_snapshot()).unwrap_err().contains("invalid parameters"));
+    for state in [0, 5] {
+        let mut wrong = run();
+        wrong.cells[0] = super::Cell { is_alive: true, state, ..super::Cell::new() };
+        assert!(World::from_snapshot(&wrong.to_snapshot()).unwrap_err().contains("outside the 5 states"));
+    }
+
+    // Tables keep no text, so their compiled transitions are checked instead.
+    let table = super::table::RuleTable::parse(
+        "@TABLE\nn_states:3\nneighborhood:vonNeumann\nsymmetries:none\n0,1,0,0,0,2\n",
+    )
+    .unwrap();
+    let world = World::new(Rules::Table(std::sync::Arc::new(table)), 4, 4, 0, 0.5);
+    assert!(World::from_snapshot(&world.to_snapshot()).is_ok());
+    let json: serde_json::Value = serde_json::from_str(&world.to_snapshot_json()).unwrap();
+    let corrupt = |edit: &dyn Fn(&mut serde_json::Value)| {
+        let mut json = json.clone();
+        edit(&mut json["world"]["rule"]["Table"]);
+        World::from_snapshot(json.to_string().as_bytes()).unwrap_err()
+    };
+    assert!(corrupt(&|table| table["outputs"][0] = 3.into()).contains("invalid parameters"));
+    assert!(corrupt(&|table| table["lut"].as_array_mut().unwrap().truncate(2)).contains("invalid parameters"));
+    assert!(corrupt(&|table| table["lut"][1].as_array_mut().unwrap().truncate(2)).contains("invalid parameters"));
+}
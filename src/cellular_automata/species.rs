@@ -0,0 +1,61 @@
+use super::Cell;
+
+pub const MAX_SPECIES: u8 = 8;
+
+pub const COLORS: [[u8; 3]; MAX_SPECIES as usize] = [
+    [255, 215, 0],
+    [220, 20, 60],
+    [30, 144, 255],
+    [50, 205, 50],
+    [255, 140, 0],
+    [186, 85, 211],
+    [0, 206, 209],
+    [245, 245, 245],
+];
+
+pub fn color(species: u8) -> [u8; 3] {
+    COLORS[(species % MAX_SPECIES) as usize]
+}
+
+/// Species of a newborn cell: the one most of its live neighbours belong to.
+/// When every present species is tied and exactly one is missing (QuadLife's
+/// three-different-parents case) the missing species is picked instead.
+pub fn inherit(parents: &[Option<&Cell>], species: u8) -> u8 {
+    let mut counts = [0u8; MAX_SPECIES as usize];
+    parents
+        .iter()
+        .flatten()
+        .filter(|c| c.is_alive)
+        .for_each(|c| counts[(c.species % species) as usize] += 1);
+    let counts = &counts[..species as usize];
+
+    let max = *counts.iter().max().unwrap_or(&0);
+    let present = counts.iter().filter(|&&c| c > 0).count();
+    let tied = counts.iter().filter(|&&c| c == max).count();
+    if present > 1 && tied == present && present + 1 == species as usize {
+        if let Some(missing) = counts.iter().position(|&c| c == 0) {
+            return missing as u8;
+        }
+    }
+    counts.iter().position(|&c| c == max).unwrap_or(0) as u8
+}
+
+pub fn populate(cell: &mut Cell, species: u8, rng: &mut impl rand::Rng) {
+    cell.species = if species > 1 {
+        rng.gen::<u8>() % species
+    } else {
+        0
+    };
+}
+
+#[test]
+fn test_inherit() {
+    let (a, b, c) = (
+        Cell { is_alive: true, species: 0, ..Cell::new() },
+        Cell { is_alive: true, species: 1, ..Cell::new() },
+        Cell { is_alive: true, species: 2, ..Cell::new() },
+    );
+    let parents = [Some(&a), None, Some(&b), Some(&c)];
+    assert_eq!(inherit(&parents, 4), 3);
+    assert_eq!(inherit(&parents, 3), 0);
+}
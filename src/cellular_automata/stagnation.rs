@@ -0,0 +1,156 @@
+use std::{
+    collections::VecDeque,
+    hash::{Hash, Hasher},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::Cell;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stagnation {
+    Extinct,
+    /// The world repeats itself every `period` generations, 1 being a still life.
+    Periodic(usize),
+}
+
+/// When a stagnating world should be reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResetPolicy {
+    Extinction,
+    /// Extinct or made only of still lifes.
+    Stable,
+    /// Extinct or oscillating with a period of at most `n`.
+    Period(usize),
+}
+
+impl ResetPolicy {
+    pub fn parse(input: &str) -> Result<ResetPolicy, String> {
+        match input {
+            "on-extinction" => Ok(ResetPolicy::Extinction),
+            "on-stable" => Ok(ResetPolicy::Stable),
+            _ => input
+                .strip_prefix("on-period<=")
+                .and_then(|n| n.parse().ok())
+                .filter(|&n| n > 0)
+                .map(ResetPolicy::Period)
+                .ok_or_else(|| format!("Unknown reset policy: {}", input)),
+        }
+    }
+
+    fn applies(&self, stagnation: Stagnation) -> bool {
+        match (self, stagnation) {
+            (_, Stagnation::Extinct) => true,
+            (ResetPolicy::Extinction, _) => false,
+            (ResetPolicy::Stable, Stagnation::Periodic(period)) => period == 1,
+            (ResetPolicy::Period(n), Stagnation::Periodic(period)) => period <= *n,
+        }
+    }
+}
+
+/// Hashes every generation to spot extinction and cycles up to `max_period`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Detector {
+    pub max_period: usize,
+    pub policy: Option<ResetPolicy>,
+    /// Generations to keep showing a stagnating world before resetting it.
+    pub grace: u64,
+    hashes: VecDeque<u64>,
+    detected: Option<(u64, Stagnation)>,
+}
+
+impl Default for Detector {
+    fn default() -> Detector {
+        Detector::new(None, 0, 30)
+    }
+}
+
+impl Detector {
+    pub fn new(policy: Option<ResetPolicy>, grace: u64, max_period: usize) -> Detector {
+        let max_period = match policy {
+            Some(ResetPolicy::Period(n)) => max_period.max(n),
+            _ => max_period.max(1),
+        };
+        Detector {
+            max_period,
+            policy,
+            grace,
+            hashes: VecDeque::with_capacity(max_period + 1),
+            detected: None,
+        }
+    }
+
+    /// Whether no more generations are remembered than the longest period.
+    pub fn is_consistent(&self) -> bool {
+        self.max_period >= 1 && self.hashes.len() <= self.max_period
+    }
+
+    pub fn stagnation(&self) -> Option<Stagnation> {
+        self.detected.map(|(_, stagnation)| stagnation)
+    }
+
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+        self.detected = None;
+    }
+
+    pub fn observe(&mut self, cells: &[Cell], epoch: u64) {
+        let stagnation = if cells.iter().all(|c| !c.is_alive) {
+            Some(Stagnation::Extinct)
+        } else {
+            let hash = hash(cells);
+            let period = self.hashes.iter().rev().position(|&h| h == hash).map(|p| p + 1);
+            if self.hashes.len() == self.max_period {
+                self.hashes.pop_front();
+            }
+            self.hashes.push_back(hash);
+            period.map(Stagnation::Periodic)
+        };
+
+        self.detected = match (self.detected, stagnation) {
+            (Some((since, old)), Some(new)) if old == new => Some((since, old)),
+            (_, Some(new)) => Some((epoch, new)),
+            (_, None) => None,
+        };
+    }
+
+    pub fn should_reset(&self, epoch: u64) -> bool {
+        match (self.policy, self.detected) {
+            (Some(policy), Some((since, stagnation))) => {
+                policy.applies(stagnation) && epoch - since >= self.grace
+            }
+            _ => false,
+        }
+    }
+}
+
+fn hash(cells: &[Cell]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for cell in cells {
+        (cell.is_alive, cell.state, cell.species).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[test]
+fn test_detect() {
+    use super::{Rules, World};
+
+    let mut world = World::new(Rules::Conway, 5, 5, 0, 0.0);
+    world.revive(1, 2);
+    world.revive(2, 2);
+    world.revive(3, 2);
+    for _ in 0..3 {
+        world.tick();
+    }
+    assert_eq!(world.period(), Some(2));
+
+    world.kill(2, 1);
+    world.kill(2, 3);
+    world.tick();
+    assert_eq!(world.stagnation(), Some(Stagnation::Extinct));
+
+    assert_eq!(ResetPolicy::parse("on-period<=3"), Ok(ResetPolicy::Period(3)));
+    assert!(ResetPolicy::Period(3).applies(Stagnation::Periodic(2)));
+    assert!(!ResetPolicy::Stable.applies(Stagnation::Periodic(2)));
+}
@@ -0,0 +1,190 @@
+use std::{collections::VecDeque, io::Write};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Age, Cell};
+
+/// Census of one generation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub epoch: u64,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+    pub density: f64,
+    /// `(min_x, min_y, max_x, max_y)` of the live cells.
+    pub bounding_box: Option<(usize, usize, usize, usize)>,
+    pub mean_age: f64,
+    pub max_age: Age,
+    /// Share of the cells that were born or died during the tick.
+    pub change_rate: f64,
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "epoch {} pop {} (+{} -{}) density {:.1}% age {:.1}/{} change {:.1}%",
+            self.epoch,
+            self.population,
+            self.births,
+            self.deaths,
+            self.density * 100.0,
+            self.mean_age,
+            self.max_age,
+            self.change_rate * 100.0,
+        )
+    }
+}
+
+/// Accumulates a `Stats` cell by cell while the world ticks.
+#[derive(Debug, Default)]
+pub struct Census {
+    population: usize,
+    births: usize,
+    deaths: usize,
+    ages: u128,
+    max_age: Age,
+    bounding_box: Option<(usize, usize, usize, usize)>,
+}
+
+impl Census {
+    pub fn count(&mut self, x: usize, y: usize, previous: &Cell, current: &Cell) {
+        match (previous.is_alive, current.is_alive) {
+            (false, true) => self.births += 1,
+            (true, false) => self.deaths += 1,
+            _ => (),
+        }
+        if !current.is_alive {
+            return;
+        }
+        self.population += 1;
+        self.ages += current.age as u128;
+        self.max_age = self.max_age.max(current.age);
+        self.bounding_box = Some(match self.bounding_box {
+            Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+            None => (x, y, x, y),
+        });
+    }
+
+    pub fn finish(self, epoch: u64, cells: usize) -> Stats {
+        let cells = cells.max(1) as f64;
+        Stats {
+            epoch,
+            population: self.population,
+            births: self.births,
+            deaths: self.deaths,
+            density: self.population as f64 / cells,
+            bounding_box: self.bounding_box,
+            mean_age: if self.population > 0 {
+                self.ages as f64 / self.population as f64
+            } else {
+                0.0
+            },
+            max_age: self.max_age,
+            change_rate: (self.births + self.deaths) as f64 / cells,
+        }
+    }
+}
+
+/// Rolling window of the last generations' stats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsHistory {
+    pub capacity: usize,
+    stats: VecDeque<Stats>,
+}
+
+impl Default for StatsHistory {
+    fn default() -> StatsHistory {
+        StatsHistory {
+            capacity: 1000,
+            stats: VecDeque::new(),
+        }
+    }
+}
+
+impl StatsHistory {
+    pub fn push(&mut self, stats: Stats) {
+        if self.stats.len() == self.capacity {
+            self.stats.pop_front();
+        }
+        self.stats.push_back(stats);
+    }
+
+    pub fn last(&self) -> Option<&Stats> {
+        self.stats.back()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Stats> {
+        self.stats.iter()
+    }
+}
+
+/// Stats written as CSV, one line per generation.
+pub struct CsvExport(std::io::LineWriter<std::fs::File>);
+
+impl CsvExport {
+    /// Opens `path` for appending, so a resumed run carries on the same file.
+    /// The header is only written to a new or empty file.
+    pub fn create(path: &std::path::Path) -> std::io::Result<CsvExport> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut out = std::io::LineWriter::new(file);
+        if is_empty {
+            writeln!(
+                out,
+                "epoch,population,births,deaths,density,min_x,min_y,max_x,max_y,mean_age,max_age,change_rate"
+            )?;
+        }
+        Ok(CsvExport(out))
+    }
+
+    pub fn write(&mut self, stats: &Stats) -> std::io::Result<()> {
+        let (x0, y0, x1, y1) = match stats.bounding_box {
+            Some((x0, y0, x1, y1)) => (x0.to_string(), y0.to_string(), x1.to_string(), y1.to_string()),
+            None => Default::default(),
+        };
+        writeln!(
+            self.0,
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            stats.epoch,
+            stats.population,
+            stats.births,
+            stats.deaths,
+            stats.density,
+            x0,
+            y0,
+            x1,
+            y1,
+            stats.mean_age,
+            stats.max_age,
+            stats.change_rate
+        )
+    }
+}
+
+#[test]
+fn test_census() {
+    use super::{Rules, World};
+
+    let mut world = World::new(Rules::Conway, 6, 6, 0, 0.0);
+    world.revive(1, 2);
+    world.revive(2, 2);
+    world.revive(3, 2);
+    world.tick();
+    let stats = world.stats();
+    assert_eq!((stats.population, stats.births, stats.deaths), (3, 2, 2));
+    assert_eq!(stats.bounding_box, Some((2, 1, 2, 3)));
+    assert_eq!(stats.max_age, 1);
+    assert_eq!(world.stats_history.iter().count(), 1);
+
+    // A second run appends below the first, under the one header.
+    let path = std::env::temp_dir().join(format!("stats-{}.csv", std::process::id()));
+    for _ in 0..2 {
+        CsvExport::create(&path).unwrap().write(&stats).unwrap();
+    }
+    let csv = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(csv.lines().count(), 3);
+    assert!(csv.starts_with("epoch,"));
+}
@@ -0,0 +1,551 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Cell, Neighbors};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T, ParseError> {
+    Err(ParseError {
+        line,
+        message: message.into(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Neighborhood {
+    Moore,
+    VonNeumann,
+    Hexagonal,
+}
+
+impl Neighborhood {
+    /// Number of neighbours, listed clockwise from north as in Golly's tables.
+    pub fn size(&self) -> usize {
+        match self {
+            Neighborhood::Moore => 8,
+            Neighborhood::VonNeumann => 4,
+            Neighborhood::Hexagonal => 6,
+        }
+    }
+}
+
+/// A Golly `@TABLE` rule, compiled into per-position bitsets so that finding
+/// the first matching transition is a handful of ANDs. Permute-symmetric
+/// tables are kept as neighbour counts instead, as listing every order of
+/// the neighbours takes up to 8! entries per transition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleTable {
+    pub name: String,
+    pub n_states: u16,
+    pub neighborhood: Neighborhood,
+    pub colors: HashMap<u8, [u8; 3]>,
+    outputs: Vec<u8>,
+    // lut[position][state] is the set of transitions accepting `state` at `position`.
+    lut: Vec<Vec<Vec<u64>>>,
+    permuted: Vec<Permuted>,
+}
+
+/// A transition of a permute-symmetric table, where the neighbours may come
+/// in any order so only how many fall in each set of states matters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Permuted {
+    center: Vec<u8>,
+    /// Sets of accepted states, each with the number of neighbours it takes.
+    counts: Vec<(Vec<u8>, usize)>,
+    output: u8,
+}
+
+impl Permuted {
+    fn new((inputs, output): Transition) -> Permuted {
+        let mut neighbors = inputs[1..].to_vec();
+        neighbors.sort();
+        let mut counts: Vec<(Vec<u8>, usize)> = Vec::new();
+        for states in neighbors {
+            match counts.last_mut() {
+                Some((last, count)) if *last == states => *count += 1,
+                _ => counts.push((states, 1)),
+            }
+        }
+        Permuted {
+            center: inputs[0].clone(),
+            counts,
+            output,
+        }
+    }
+
+    fn matches(&self, states: &[u8]) -> bool {
+        if !self.center.contains(&states[0]) {
+            return false;
+        }
+        // At most eight neighbours and so at most eight sets, kept on the
+        // stack as this runs for every cell.
+        let neighbors = &states[1..];
+        let mut placement = Placement {
+            set_of: [usize::MAX; 8],
+            used: [0; 8],
+        };
+        (0..neighbors.len()).all(|i| self.place(i, neighbors, &mut placement, &mut [false; 8]))
+    }
+
+    /// Finds neighbour `i` room in a set accepting its state, moving the
+    /// neighbours already placed along if needed: a matching of the
+    /// neighbours to the counted sets.
+    fn place(&self, i: usize, neighbors: &[u8], placement: &mut Placement, visited: &mut [bool; 8]) -> bool {
+        for (set, (states, count)) in self.counts.iter().enumerate() {
+            if visited[set] || !states.contains(&neighbors[i]) {
+                continue;
+            }
+            visited[set] = true;
+            if placement.used[set] < *count {
+                placement.used[set] += 1;
+                placement.set_of[i] = set;
+                return true;
+            }
+            for k in 0..neighbors.len() {
+                if placement.set_of[k] == set && self.place(k, neighbors, placement, visited) {
+                    // `k` moved to another set, `i` takes its place.
+                    placement.set_of[i] = set;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Which set each neighbour is placed in and how full each set is.
+struct Placement {
+    set_of: [usize; 8],
+    used: [usize; 8],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Token {
+    State(u8),
+    Var(String),
+}
+
+type Transition = (Vec<Vec<u8>>, u8);
+
+impl RuleTable {
+    pub fn load(path: &std::path::Path) -> Result<RuleTable, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        RuleTable::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(source: &str) -> Result<RuleTable, ParseError> {
+        let mut name = None;
+        let mut section = "";
+        let mut has_table = false;
+        let mut n_states = None;
+        let mut neighborhood = None;
+        let mut symmetries = None;
+        let mut vars: HashMap<String, Vec<u8>> = HashMap::new();
+        let mut transitions: Vec<Transition> = Vec::new();
+        let mut permuted: Vec<Permuted> = Vec::new();
+        let mut seen: HashSet<Transition> = HashSet::new();
+        let mut colors = HashMap::new();
+
+        for (index, raw) in source.lines().enumerate() {
+            let line = index + 1;
+            let text = raw.split('#').next().unwrap_or("").trim();
+            if text.is_empty() {
+                continue;
+            }
+            if let Some(header) = text.strip_prefix('@') {
+                let mut words = header.split_whitespace();
+                section = match words.next().unwrap_or("") {
+                    "RULE" => {
+                        name = words.next().map(String::from);
+                        "RULE"
+                    }
+                    "TABLE" => {
+                        has_table = true;
+                        "TABLE"
+                    }
+                    "COLORS" => "COLORS",
+                    "TREE" => return error(line, "@TREE rules are not supported, use @TABLE"),
+                    _ => "",
+                };
+                continue;
+            }
+
+            match section {
+                "TABLE" => {
+                    if let Some((key, value)) = text.split_once(':') {
+                        let value = value.trim();
+                        match key.trim() {
+                            "n_states" => {
+                                n_states = match value.parse::<u16>() {
+                                    Ok(n) if (2..=256).contains(&n) => Some((n - 1) as u8),
+                                    _ => return error(line, format!("invalid n_states '{}', expected 2..256", value)),
+                                }
+                            }
+                            "neighborhood" => {
+                                neighborhood = Some(match value {
+                                    "Moore" => Neighborhood::Moore,
+                                    "vonNeumann" => Neighborhood::VonNeumann,
+                                    "hexagonal" => Neighborhood::Hexagonal,
+                                    _ => return error(line, format!("unsupported neighborhood '{}'", value)),
+                                })
+                            }
+                            "symmetries" if !transitions.is_empty() || !permuted.is_empty() => {
+                                return error(line, "symmetries must be declared before the transitions")
+                            }
+                            "symmetries" => symmetries = Some((line, value.to_string())),
+                            _ => return error(line, format!("unknown table setting '{}'", key.trim())),
+                        }
+                        continue;
+                    }
+
+                    let (Some(max_state), Some(neighborhood)) = (n_states, neighborhood) else {
+                        return error(line, "n_states and neighborhood must be declared before variables and transitions");
+                    };
+                    if let Some(declaration) = text.strip_prefix("var ") {
+                        let Some((var, values)) = declaration.split_once('=') else {
+                            return error(line, "expected 'var name={values}'");
+                        };
+                        let values = values.trim();
+                        let Some(values) = values.strip_prefix('{').and_then(|v| v.strip_suffix('}')) else {
+                            return error(line, "variable values must be enclosed in braces");
+                        };
+                        let mut states = Vec::new();
+                        for value in values.split(',').map(str::trim) {
+                            match parse_token(value, max_state, &vars, line)? {
+                                Token::State(state) => states.push(state),
+                                Token::Var(other) => states.extend(&vars[&other]),
+                            }
+                        }
+                        vars.insert(var.trim().to_string(), states);
+                        continue;
+                    }
+
+                    let size = neighborhood.size() + 2;
+                    let words: Vec<&str> = if text.contains(',') || text.contains(char::is_whitespace) {
+                        text.split(|c: char| c == ',' || c.is_whitespace())
+                            .filter(|w| !w.is_empty())
+                            .collect()
+                    } else if max_state < 10 && text.len() == size {
+                        (0..text.len()).map(|i| &text[i..i + 1]).collect()
+                    } else {
+                        vec![text]
+                    };
+                    if words.len() != size {
+                        return error(line, format!("expected {} entries in transition, found {}", size, words.len()));
+                    }
+                    let tokens = words
+                        .iter()
+                        .map(|w| parse_token(w, max_state, &vars, line))
+                        .collect::<Result<Vec<Token>, ParseError>>()?;
+
+                    let (symmetry_line, symmetry) = symmetries.clone().unwrap_or((line, "none".into()));
+                    let group = symmetry_group(&symmetry, neighborhood.size())
+                        .ok_or_else(|| ParseError {
+                            line: symmetry_line,
+                            message: format!("unsupported symmetries '{}' for {:?}", symmetry, neighborhood),
+                        })?;
+
+                    for (inputs, output) in bind(&tokens, &vars, line)? {
+                        match &group {
+                            SymmetryGroup::Permute => {
+                                let transition = Permuted::new((inputs, output));
+                                if !permuted.contains(&transition) {
+                                    permuted.push(transition);
+                                }
+                            }
+                            SymmetryGroup::Permutations(perms) => {
+                                for arranged in arrange(perms, &inputs) {
+                                    let transition = (arranged, output);
+                                    if seen.insert(transition.clone()) {
+                                        transitions.push(transition);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                "COLORS" => {
+                    let numbers = text
+                        .split_whitespace()
+                        .map(|w| w.parse::<u8>())
+                        .collect::<Result<Vec<u8>, _>>();
+                    match numbers.as_deref() {
+                        Ok([state, r, g, b]) => {
+                            colors.insert(*state, [*r, *g, *b]);
+                        }
+                        // Gradient lines (`r0 g0 b0 r1 g1 b1`) are left to the default palette.
+                        Ok([_, _, _, _, _, _]) => (),
+                        _ => return error(line, "expected 'state r g b' in @COLORS"),
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if !has_table {
+            return error(source.lines().count().max(1), "missing @TABLE section");
+        }
+        let Some(max_state) = n_states else {
+            return error(1, "missing n_states in @TABLE");
+        };
+        let Some(neighborhood) = neighborhood else {
+            return error(1, "missing neighborhood in @TABLE");
+        };
+
+        let words = transitions.len().div_ceil(64);
+        let mut lut = vec![vec![vec![0u64; words]; max_state as usize + 1]; neighborhood.size() + 1];
+        for (t, (inputs, _)) in transitions.iter().enumerate() {
+            for (position, states) in inputs.iter().enumerate() {
+                for &state in states {
+                    lut[position][state as usize][t / 64] |= 1 << (t % 64);
+                }
+            }
+        }
+
+        Ok(RuleTable {
+            name: name.unwrap_or_else(|| "table".into()),
+            n_states: max_state as u16 + 1,
+            neighborhood,
+            colors,
+            outputs: transitions.into_iter().map(|(_, output)| output).collect(),
+            lut,
+            permuted,
+        })
+    }
+
+    /// Whether the compiled transitions have the shape `parse` gives them,
+    /// covering every position and state and naming only states in the table.
+    pub fn is_consistent(&self) -> bool {
+        let n_states = self.n_states as usize;
+        let size = self.neighborhood.size();
+        let words = self.outputs.len().div_ceil(64);
+        let known = |state: &u8| (*state as usize) < n_states;
+        (2..=256).contains(&n_states)
+            && self.outputs.iter().all(known)
+            && self.lut.len() == size + 1
+            && self.lut.iter().all(|states| states.len() == n_states && states.iter().all(|bits| bits.len() == words))
+            && self.permuted.iter().all(|transition| {
+                known(&transition.output)
+                    && transition.center.iter().all(known)
+                    && transition.counts.iter().all(|(states, count)| *count > 0 && states.iter().all(known))
+                    && transition.counts.iter().map(|(_, count)| count).sum::<usize>() == size
+            })
+    }
+
+    /// Next state of a cell given its state followed by its neighbours' states.
+    pub fn next(&self, states: &[u8]) -> u8 {
+        if !self.permuted.is_empty() {
+            return self
+                .permuted
+                .iter()
+                .find(|transition| transition.matches(states))
+                .map_or(states[0], |transition| transition.output);
+        }
+        for word in 0..self.outputs.len().div_ceil(64) {
+            let mut matches = !0u64;
+            for (position, &state) in states.iter().enumerate() {
+                matches &= self.lut[position][state as usize][word];
+                if matches == 0 {
+                    break;
+                }
+            }
+            if matches != 0 {
+                return self.outputs[word * 64 + matches.trailing_zeros() as usize];
+            }
+        }
+        states[0]
+    }
+
+    /// The cell's state then its neighbours' in the table's order, written
+    /// into `states`, with cells off the world reading as 0.
+    pub fn neighbor_states<'a>(&self, state: u8, neighbors: &Neighbors, states: &'a mut [u8; 9]) -> &'a [u8] {
+        let cells: &[Option<&Cell>] = match self.neighborhood {
+            Neighborhood::Moore => &[
+                neighbors.up,
+                neighbors.up_right,
+                neighbors.right,
+                neighbors.down_right,
+                neighbors.down,
+                neighbors.down_left,
+                neighbors.left,
+                neighbors.up_left,
+            ],
+            Neighborhood::VonNeumann => &[neighbors.up, neighbors.right, neighbors.down, neighbors.left],
+            Neighborhood::Hexagonal => &[
+                neighbors.up,
+                neighbors.right,
+                neighbors.down_right,
+                neighbors.down,
+                neighbors.left,
+                neighbors.up_left,
+            ],
+        };
+        states[0] = state;
+        for (state, cell) in states[1..].iter_mut().zip(cells) {
+            *state = cell.map_or(0, |c| c.state);
+        }
+        &states[..cells.len() + 1]
+    }
+
+    pub fn color(&self, state: u8) -> Option<[u8; 3]> {
+        self.colors.get(&state).copied()
+    }
+}
+
+fn parse_token(word: &str, max_state: u8, vars: &HashMap<String, Vec<u8>>, line: usize) -> Result<Token, ParseError> {
+    if let Ok(state) = word.parse::<u16>() {
+        if state > max_state as u16 {
+            return error(line, format!("state {} is out of range 0..={}", state, max_state));
+        }
+        return Ok(Token::State(state as u8));
+    }
+    if vars.contains_key(word) {
+        Ok(Token::Var(word.to_string()))
+    } else {
+        error(line, format!("undefined variable '{}'", word))
+    }
+}
+
+/// Golly binds variables: a name used more than once in a transition (output
+/// included) takes the same value everywhere, so those are enumerated here and
+/// the remaining ones stay as sets of accepted states.
+fn bind(tokens: &[Token], vars: &HashMap<String, Vec<u8>>, line: usize) -> Result<Vec<Transition>, ParseError> {
+    let (inputs, output) = tokens.split_at(tokens.len() - 1);
+    let mut bound: Vec<&String> = Vec::new();
+    for token in tokens {
+        if let Token::Var(var) = token {
+            if tokens.iter().filter(|t| *t == token).count() > 1 && !bound.contains(&var) {
+                bound.push(var);
+            }
+        }
+    }
+    if let Token::Var(var) = &output[0] {
+        if !inputs.contains(&output[0]) {
+            return error(line, format!("output variable '{}' does not appear in the inputs", var));
+        }
+    }
+
+    let mut assignments: Vec<HashMap<&String, u8>> = vec![HashMap::new()];
+    for var in bound {
+        assignments = assignments
+            .into_iter()
+            .flat_map(|a| {
+                vars[var].iter().map(move |&value| {
+                    let mut a = a.clone();
+                    a.insert(var, value);
+                    a
+                })
+            })
+            .collect();
+    }
+
+    Ok(assignments
+        .iter()
+        .map(|assignment| {
+            let resolve = |token: &Token| match token {
+                Token::State(state) => vec![*state],
+                Token::Var(var) => assignment
+                    .get(var)
+                    .map(|&value| vec![value])
+                    .unwrap_or_else(|| vars[var].clone()),
+            };
+            let inputs = inputs
+                .iter()
+                .map(|t| {
+                    let mut states = resolve(t);
+                    states.sort_unstable();
+                    states.dedup();
+                    states
+                })
+                .collect();
+            (inputs, resolve(&output[0])[0])
+        })
+        .collect())
+}
+
+enum SymmetryGroup {
+    Permutations(Vec<Vec<usize>>),
+    Permute,
+}
+
+/// The rearrangements of the neighbour entries (the centre stays first).
+fn arrange(perms: &[Vec<usize>], inputs: &[Vec<u8>]) -> Vec<Vec<Vec<u8>>> {
+    perms
+        .iter()
+        .map(|perm| {
+            std::iter::once(inputs[0].clone())
+                .chain(perm.iter().map(|&p| inputs[1 + p].clone()))
+                .collect()
+        })
+        .collect()
+}
+
+fn symmetry_group(name: &str, size: usize) -> Option<SymmetryGroup> {
+    let rotation = |by: usize| (0..size).map(|i| (i + by) % size).collect::<Vec<usize>>();
+    let reflect = |perm: &Vec<usize>| perm.iter().map(|&i| (size - i) % size).collect::<Vec<usize>>();
+    let rotations = |n: usize| -> Option<Vec<Vec<usize>>> {
+        size.is_multiple_of(n).then(|| (0..n).map(|r| rotation(r * size / n)).collect())
+    };
+
+    let perms = match name {
+        "none" => vec![rotation(0)],
+        "permute" => return Some(SymmetryGroup::Permute),
+        "reflect_horizontal" => vec![rotation(0), reflect(&rotation(0))],
+        _ => {
+            let (n, reflected) = match name.strip_suffix("reflect") {
+                Some(rotate) => (rotate.strip_prefix("rotate")?, true),
+                None => (name.strip_prefix("rotate")?, false),
+            };
+            let mut perms = rotations(n.parse().ok()?)?;
+            if reflected {
+                perms.extend(perms.iter().map(reflect).collect::<Vec<_>>());
+            }
+            perms
+        }
+    };
+    Some(SymmetryGroup::Permutations(perms))
+}
+
+#[test]
+fn test_parse() {
+    let table = RuleTable::parse(
+        "@RULE Life\n@TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:permute\nvar a={0,1}\nvar b={a}\nvar c={a}\nvar d={a}\nvar e={a}\nvar f={a}\n\
+         0,1,1,1,0,0,0,0,0,1\n1,1,1,0,0,0,0,0,0,1\n1,1,1,1,0,0,0,0,0,1\n1,a,b,c,d,e,f,0,0,0\n@COLORS\n1 255 255 0\n",
+    )
+    .unwrap();
+    assert_eq!(table.name, "Life");
+    assert_eq!(table.color(1), Some([255, 255, 0]));
+    assert_eq!(table.next(&[0, 0, 1, 0, 1, 0, 0, 1, 0]), 1);
+    assert_eq!(table.next(&[1, 0, 0, 0, 0, 0, 1, 0, 1]), 1);
+    assert_eq!(table.next(&[1, 1, 1, 1, 1, 0, 0, 0, 0]), 0);
+    assert_eq!(table.next(&[0, 1, 1, 0, 0, 0, 0, 0, 0]), 0);
+    assert_eq!(table.permuted.len(), 4);
+
+    // A neighbour in state 1 or 2 plus one in state 2 only: the 2 must go
+    // to the second set for the 1 to fit the first.
+    let table = RuleTable::parse(
+        "@TABLE\nn_states:3\nneighborhood:vonNeumann\nsymmetries:permute\nvar a={1,2}\n0,a,2,0,0,1\n",
+    )
+    .unwrap();
+    assert_eq!(table.next(&[0, 2, 0, 1, 0]), 1);
+    assert_eq!(table.next(&[0, 0, 2, 0, 2]), 1);
+    assert_eq!(table.next(&[0, 1, 0, 1, 0]), 0);
+
+    let err = RuleTable::parse("@TABLE\nn_states:2\nneighborhood:Moore\n0,1,x,0,0,0,0,0,0,1\n").unwrap_err();
+    assert_eq!(err.line, 4);
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+use super::Cell;
+
+/// Birth/survival rule on a triangular tiling, keyed by how many of the 3
+/// edge neighbours and of the 9 vertex neighbours are alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TriangularRule {
+    pub birth: u64,
+    pub survival: u64,
+}
+
+const fn bit(edge: u8, vertex: u8) -> u64 {
+    1 << (edge * 10 + vertex)
+}
+
+impl TriangularRule {
+    /// Every (edge, vertex) split of the given totals of live neighbours.
+    pub const fn totalistic(birth: &[u8], survival: &[u8]) -> TriangularRule {
+        const fn mask(totals: &[u8]) -> u64 {
+            let mut mask = 0;
+            let mut i = 0;
+            while i < totals.len() {
+                let mut edge = 0;
+                while edge <= 3 && edge <= totals[i] {
+                    if totals[i] - edge <= 9 {
+                        mask |= bit(edge, totals[i] - edge);
+                    }
+                    edge += 1;
+                }
+                i += 1;
+            }
+            mask
+        }
+        TriangularRule {
+            birth: mask(birth),
+            survival: mask(survival),
+        }
+    }
+
+    /// Parses `B<ev>,<ev>.../S<ev>,...` where each term is an edge count
+    /// (0-3) followed by a vertex count (0-9), e.g. `B13,22/S12,21,30`.
+    pub fn parse(input: &str) -> Result<TriangularRule, String> {
+        let unknown = || format!("Unknown triangular rulestring: {}", input);
+        let terms = |list: &str| -> Option<u64> {
+            list.split(',')
+                .filter(|term| !term.is_empty())
+                .map(|term| match term.as_bytes() {
+                    [e @ b'0'..=b'3', v @ b'0'..=b'9'] => Some(bit(e - b'0', v - b'0')),
+                    _ => None,
+                })
+                .try_fold(0, |mask, bit| bit.map(|bit| mask | bit))
+        };
+        let (birth, survival) = input.split_once('/').ok_or_else(unknown)?;
+        let birth = birth.strip_prefix(['B', 'b']).ok_or_else(unknown)?;
+        let survival = survival.strip_prefix(['S', 's']).ok_or_else(unknown)?;
+        Ok(TriangularRule {
+            birth: terms(birth).ok_or_else(unknown)?,
+            survival: terms(survival).ok_or_else(unknown)?,
+        })
+    }
+
+    pub fn next(&self, is_alive: bool, edge: u8, vertex: u8) -> bool {
+        let mask = if is_alive { self.survival } else { self.birth };
+        mask & bit(edge, vertex) != 0
+    }
+}
+
+impl std::fmt::Display for TriangularRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let terms = |mask: u64| {
+            (0..40)
+                .filter(|b| mask & (1 << b) != 0)
+                .map(|b| format!("{}{}", b / 10, b % 10))
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        write!(f, "B{}/S{}", terms(self.birth), terms(self.survival))
+    }
+}
+
+/// Cells whose `x + y` is even point up, the others point down.
+pub fn points_up(x: usize, y: usize) -> bool {
+    (x + y).is_multiple_of(2)
+}
+
+/// The 3 edge neighbours and the 9 vertex neighbours of the cell at `i`.
+pub fn neighbors(cells: &[Cell], width: usize, height: usize, i: usize) -> (Vec<&Cell>, Vec<&Cell>) {
+    let (x, y) = ((i % width) as isize, (i / width) as isize);
+    // Row on the side of the triangle's base, and on the side of its apex.
+    let (base, apex) = if points_up(i % width, i / width) { (1, -1) } else { (-1, 1) };
+    let at = |dx: isize, dy: isize| {
+        let (x, y) = (x + dx, y + dy);
+        (x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height)
+            .then(|| &cells[y as usize * width + x as usize])
+    };
+    let edge = [at(-1, 0), at(1, 0), at(0, base)];
+    let vertex = [
+        at(-2, 0),
+        at(2, 0),
+        at(-1, apex),
+        at(0, apex),
+        at(1, apex),
+        at(-2, base),
+        at(-1, base),
+        at(1, base),
+        at(2, base),
+    ];
+    (
+        edge.into_iter().flatten().collect(),
+        vertex.into_iter().flatten().collect(),
+    )
+}
+
+pub fn tick(cell: &mut Cell, edge: &[&Cell], vertex: &[&Cell], rule: &TriangularRule) {
+    let alive = |cells: &[&Cell]| cells.iter().filter(|c| c.is_alive).count() as u8;
+    cell.is_alive = rule.next(cell.is_alive, alive(edge), alive(vertex));
+    cell.get_older();
+}
+
+#[test]
+fn test_parse() {
+    let rule = TriangularRule::parse("B13,22/S12,21,30").unwrap();
+    assert!(rule.next(false, 1, 3) && !rule.next(false, 1, 2));
+    assert!(rule.next(true, 3, 0));
+    assert_eq!(rule.to_string(), "B13,22/S12,21,30");
+    assert_eq!(TriangularRule::parse(&rule.to_string()), Ok(rule));
+    assert!(TriangularRule::parse("B43/S").is_err());
+}
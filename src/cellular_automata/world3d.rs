@@ -0,0 +1,272 @@
+use std::io::Write;
+
+use rayon::prelude::*;
+
+use crate::canvas::{Canvas, Pixel};
+
+/// 3D Life-like rule in `S/B/C/N` notation: survival and birth neighbour
+/// counts, number of states (cells older than 1 decay like in Generations) and
+/// neighbourhood, `M` for the 26 Moore neighbours or `N` for the 6 faces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule3D {
+    pub survival: u32,
+    pub birth: u32,
+    pub states: u8,
+    pub moore: bool,
+}
+
+impl Rule3D {
+    pub fn parse(input: &str) -> Result<Rule3D, String> {
+        let unknown = || format!("Unknown 3D rule: {}", input);
+        match input {
+            "445" => return Rule3D::parse("4/4/5/M"),
+            "clouds" => return Rule3D::parse("13-26/13-14,17-19/2/M"),
+            "amoeba" => return Rule3D::parse("9-26/5-7,12-13,15/5/M"),
+            "pyroclastic" => return Rule3D::parse("4-7/6-8/10/M"),
+            _ => (),
+        }
+
+        // Bays' notation: survival lower/upper bound then birth lower/upper bound.
+        if let [el, eu, fl, fu] = input.as_bytes() {
+            if input.bytes().all(|b| b.is_ascii_digit()) {
+                let range = |l: u8, u: u8| ((l - b'0')..=(u - b'0')).fold(0, |mask, n| mask | 1 << n);
+                return Ok(Rule3D {
+                    survival: range(*el, *eu),
+                    birth: range(*fl, *fu),
+                    states: 2,
+                    moore: true,
+                });
+            }
+        }
+
+        let parts: Vec<&str> = input.split('/').collect();
+        let [survival, birth, states, neighborhood] = parts.as_slice() else {
+            return Err(unknown());
+        };
+        Ok(Rule3D {
+            survival: counts(survival).ok_or_else(unknown)?,
+            birth: counts(birth).ok_or_else(unknown)?,
+            states: states.parse().ok().filter(|&s| s >= 2).ok_or_else(unknown)?,
+            moore: match *neighborhood {
+                "M" | "m" => true,
+                "N" | "n" => false,
+                _ => return Err(unknown()),
+            },
+        })
+    }
+}
+
+/// Comma-separated counts or ranges of counts, e.g. `5-7,12-13,15`.
+fn counts(list: &str) -> Option<u32> {
+    list.split(',')
+        .filter(|term| !term.is_empty())
+        .try_fold(0u32, |mask, term| {
+            let (low, high) = term.split_once('-').unwrap_or((term, term));
+            let (low, high) = (low.parse::<u32>().ok()?, high.parse::<u32>().ok()?);
+            (low <= high && high <= 26).then(|| (low..=high).fold(mask, |mask, n| mask | 1 << n))
+        })
+}
+
+fn ranges(mask: u32) -> String {
+    let mut terms = Vec::new();
+    let mut n = 0;
+    while n <= 26 {
+        if mask & (1 << n) != 0 {
+            let start = n;
+            while n < 26 && mask & (1 << (n + 1)) != 0 {
+                n += 1;
+            }
+            terms.push(if start == n { format!("{}", n) } else { format!("{}-{}", start, n) });
+        }
+        n += 1;
+    }
+    terms.join(",")
+}
+
+impl std::fmt::Display for Rule3D {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let neighborhood = if self.moore { "M" } else { "N" };
+        write!(f, "{}/{}/{}/{}", ranges(self.survival), ranges(self.birth), self.states, neighborhood)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum View3D {
+    /// A single z layer.
+    Slice,
+    /// The nearest live cell along z, shaded by its depth.
+    Projection,
+}
+
+#[derive(Debug)]
+pub struct World3D {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub rule: Rule3D,
+    /// 0 is dead, 1 alive and higher states are decaying.
+    pub cells: Vec<u8>,
+    pub epoch: u64,
+    pub pop_rate: f32,
+}
+
+impl World3D {
+    pub fn new(rule: Rule3D, width: usize, height: usize, depth: usize, pop_rate: f32) -> World3D {
+        World3D {
+            width,
+            height,
+            depth,
+            rule,
+            cells: vec![0; width * height * depth],
+            epoch: 0,
+            pop_rate,
+        }
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.height + y) * self.width + x
+    }
+
+    fn alive_neighbors(&self, x: usize, y: usize, z: usize) -> u8 {
+        let mut alive = 0;
+        for dz in -1..=1isize {
+            for dy in -1..=1isize {
+                for dx in -1..=1isize {
+                    let distance = dx.abs() + dy.abs() + dz.abs();
+                    if distance == 0 || (!self.rule.moore && distance > 1) {
+                        continue;
+                    }
+                    let (nx, ny, nz) = (x as isize + dx, y as isize + dy, z as isize + dz);
+                    if nx < 0 || ny < 0 || nz < 0 {
+                        continue;
+                    }
+                    let (nx, ny, nz) = (nx as usize, ny as usize, nz as usize);
+                    if nx < self.width && ny < self.height && nz < self.depth && self.cells[self.index(nx, ny, nz)] == 1 {
+                        alive += 1;
+                    }
+                }
+            }
+        }
+        alive
+    }
+
+    pub fn tick(&mut self) {
+        self.epoch += 1;
+        let cells = (0..self.cells.len())
+            .into_par_iter()
+            .map(|i| {
+                let (x, y, z) = (i % self.width, (i / self.width) % self.height, i / (self.width * self.height));
+                let alive = self.alive_neighbors(x, y, z);
+                match self.cells[i] {
+                    0 if self.rule.birth & (1 << alive) != 0 => 1,
+                    0 => 0,
+                    1 if self.rule.survival & (1 << alive) != 0 => 1,
+                    state => (state + 1) % self.rule.states,
+                }
+            })
+            .collect();
+        self.cells = cells;
+    }
+
+    /// Fills the central cube, half the world's size, with random live cells.
+    pub fn populate(&mut self) {
+        let (w, h, d) = (self.width, self.height, self.depth);
+        for z in d / 4..d - d / 4 {
+            for y in h / 4..h - h / 4 {
+                for x in w / 4..w - w / 4 {
+                    let i = self.index(x, y, z);
+                    self.cells[i] = (rand::random::<f32>() < self.pop_rate) as u8;
+                }
+            }
+        }
+    }
+
+    /// Shade of every `(x, y)` column as seen through `view`, in `[0, 1]`, or
+    /// `None` where nothing is visible.
+    pub fn view(&self, view: View3D, slice: usize) -> Vec<Option<f64>> {
+        let slice = slice.min(self.depth - 1);
+        (0..self.width * self.height)
+            .map(|i| {
+                let (x, y) = (i % self.width, i / self.width);
+                match view {
+                    View3D::Slice => match self.cells[self.index(x, y, slice)] {
+                        0 => None,
+                        state => Some(1.0 - (state - 1) as f64 / self.rule.states as f64),
+                    },
+                    View3D::Projection => (0..self.depth)
+                        .find(|&z| self.cells[self.index(x, y, z)] != 0)
+                        .map(|z| 1.0 - z as f64 / self.depth as f64),
+                }
+            })
+            .collect()
+    }
+
+    pub fn canvas(&self, view: View3D, slice: usize) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+        canvas.colored = true;
+        for (i, shade) in self.view(view, slice).into_iter().enumerate() {
+            if let Some(shade) = shade {
+                let level = (64.0 + 191.0 * shade) as u8;
+                let pixel = Pixel { r: level, g: level, b: level, a: 255 };
+                canvas.draw_pixel(i % self.width, i / self.width, pixel);
+            }
+        }
+        canvas
+    }
+
+    /// Writes the live and decaying cells as MagicaVoxel when `path` ends in
+    /// `.vox`, or as `x y z state` lines otherwise.
+    pub fn export_voxels(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let voxels: Vec<(usize, usize, usize, u8)> = (0..self.cells.len())
+            .filter(|&i| self.cells[i] != 0)
+            .map(|i| (i % self.width, (i / self.width) % self.height, i / (self.width * self.height), self.cells[i]))
+            .collect();
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+        if path.extension().is_some_and(|ext| ext == "vox") {
+            if self.width > 256 || self.height > 256 || self.depth > 256 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "MagicaVoxel models are limited to 256 cells per side",
+                ));
+            }
+            let u32le = |n: usize| (n as u32).to_le_bytes();
+            let size_chunk = 12 + 12;
+            let xyzi_chunk = 12 + 4 + 4 * voxels.len();
+            file.write_all(b"VOX ")?;
+            file.write_all(&u32le(150))?;
+            file.write_all(b"MAIN")?;
+            file.write_all(&u32le(0))?;
+            file.write_all(&u32le(size_chunk + xyzi_chunk))?;
+            file.write_all(b"SIZE")?;
+            file.write_all(&u32le(12))?;
+            file.write_all(&u32le(0))?;
+            // MagicaVoxel is z-up, so our y goes to its z.
+            for n in [self.width, self.depth, self.height] {
+                file.write_all(&u32le(n))?;
+            }
+            file.write_all(b"XYZI")?;
+            file.write_all(&u32le(4 + 4 * voxels.len()))?;
+            file.write_all(&u32le(0))?;
+            file.write_all(&u32le(voxels.len()))?;
+            for (x, y, z, state) in voxels {
+                file.write_all(&[x as u8, z as u8, (self.height - 1 - y) as u8, state])?;
+            }
+        } else {
+            writeln!(file, "# {}x{}x{} {} epoch {}", self.width, self.height, self.depth, self.rule, self.epoch)?;
+            for (x, y, z, state) in voxels {
+                writeln!(file, "{} {} {} {}", x, y, z, state)?;
+            }
+        }
+        file.flush()
+    }
+}
+
+#[test]
+fn test_parse() {
+    let rule = Rule3D::parse("445").unwrap();
+    assert_eq!(rule, Rule3D { survival: 1 << 4, birth: 1 << 4, states: 5, moore: true });
+    assert_eq!(Rule3D::parse("9-26/5-7,12-13,15/5/M").unwrap().to_string(), "9-26/5-7,12-13,15/5/M");
+    assert_eq!(Rule3D::parse("4555").unwrap().to_string(), "4-5/5/2/M");
+    assert!(Rule3D::parse("4/4/1/M").is_err());
+}
@@ -0,0 +1,148 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::cellular_automata::World;
+
+/// Name the next checkpoint is written under before being renamed, so a
+/// crash never leaves a partial checkpoint behind.
+const PARTIAL: &str = "checkpoint.partial";
+
+/// Snapshots written every `every` generations into a directory, keeping the
+/// `keep` newest of this run. Each run numbers its checkpoints apart, so a
+/// new run in the same directory neither deletes an older run's nor is
+/// mistaken for it.
+pub struct Checkpoints {
+    dir: PathBuf,
+    every: u64,
+    keep: usize,
+    run: u64,
+}
+
+impl Checkpoints {
+    /// Checkpoints for `run`, or for a new run after those already in `dir`.
+    pub fn new(dir: &Path, every: u64, keep: usize, run: Option<u64>) -> std::io::Result<Checkpoints> {
+        std::fs::create_dir_all(dir)?;
+        let run = run.unwrap_or_else(|| list(dir).first().map_or(0, |checkpoint| checkpoint.run + 1));
+        Ok(Checkpoints {
+            dir: dir.to_path_buf(),
+            every: every.max(1),
+            keep: keep.max(1),
+            run,
+        })
+    }
+
+    /// Saves a checkpoint when the world reaches a multiple of `every`.
+    pub fn tick(&self, world: &World) -> std::io::Result<()> {
+        if !world.epoch.is_multiple_of(self.every) {
+            return Ok(());
+        }
+        self.save(world)?;
+        let ours = list(&self.dir).into_iter().filter(|checkpoint| checkpoint.run == self.run);
+        for old in ours.skip(self.keep) {
+            std::fs::remove_file(old.path)?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, world: &World) -> std::io::Result<()> {
+        let partial = self.dir.join(PARTIAL);
+        let mut file = std::fs::File::create(&partial)?;
+        file.write_all(&world.to_snapshot())?;
+        file.sync_all()?;
+        std::fs::rename(partial, self.dir.join(format!("world-{:010}-{:020}.snapshot", self.run, world.epoch)))
+    }
+}
+
+struct Checkpoint {
+    run: u64,
+    epoch: u64,
+    path: PathBuf,
+}
+
+/// Checkpoints in `dir`, newest run first and newest first within a run.
+fn list(dir: &Path) -> Vec<Checkpoint> {
+    let mut checkpoints: Vec<Checkpoint> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let (run, epoch) = name.strip_prefix("world-")?.strip_suffix(".snapshot")?.split_once('-')?;
+            Some(Checkpoint {
+                run: run.parse().ok()?,
+                epoch: epoch.parse().ok()?,
+                path,
+            })
+        })
+        .collect();
+    checkpoints.sort_unstable_by_key(|checkpoint| std::cmp::Reverse((checkpoint.run, checkpoint.epoch)));
+    checkpoints
+}
+
+/// The newest checkpoint in `dir` that loads and the run it belongs to,
+/// skipping and reporting the corrupt ones.
+pub fn resume(dir: &Path) -> Option<(World, u64)> {
+    list(dir).into_iter().find_map(|checkpoint| match World::load_snapshot(&checkpoint.path) {
+        Ok(world) => {
+            eprintln!("resuming from {}", checkpoint.path.display());
+            Some((world, checkpoint.run))
+        }
+        Err(e) => {
+            eprintln!("skipping {}", e);
+            None
+        }
+    })
+}
+
+#[test]
+fn test_rotation() {
+    use crate::cellular_automata::{history::History, Rules};
+
+    let dir = std::env::temp_dir().join(format!("checkpoints-{}", std::process::id()));
+    let run = |resuming: bool, generations: usize| {
+        let resumed = if resuming { resume(&dir) } else { None };
+        let checkpoints = Checkpoints::new(&dir, 5, 2, resumed.as_ref().map(|(_, run)| *run)).unwrap();
+        let mut world = resumed.map(|(world, _)| world).unwrap_or_else(|| {
+            let mut world = World::new(Rules::Conway, 16, 16, 0, 0.3);
+            world.history = History::new(0);
+            world.populate();
+            world
+        });
+        for _ in 0..generations {
+            world.tick();
+            checkpoints.tick(&world).unwrap();
+        }
+    };
+    let names = || -> Vec<String> {
+        list(&dir).iter().map(|checkpoint| checkpoint.path.file_name().unwrap().to_string_lossy().into()).collect()
+    };
+    run(false, 20);
+    assert_eq!(names(), ["world-0000000000-00000000000000000020.snapshot", "world-0000000000-00000000000000000015.snapshot"]);
+
+    // A second run keeps the first one's checkpoints, and is the one resumed
+    // even though it has not got as far.
+    run(false, 10);
+    assert_eq!(
+        names(),
+        [
+            "world-0000000001-00000000000000000010.snapshot",
+            "world-0000000001-00000000000000000005.snapshot",
+            "world-0000000000-00000000000000000020.snapshot",
+            "world-0000000000-00000000000000000015.snapshot",
+        ]
+    );
+    assert_eq!(resume(&dir).map(|(world, run)| (world.epoch, run)), Some((10, 1)));
+    run(true, 5);
+    assert_eq!(names()[..2], ["world-0000000001-00000000000000000015.snapshot", "world-0000000001-00000000000000000010.snapshot"]);
+    assert_eq!(names().len(), 4);
+
+    // A truncated newest checkpoint is skipped for the one before it.
+    let newest = dir.join(&names()[0]);
+    let bytes = std::fs::read(&newest).unwrap();
+    std::fs::write(&newest, &bytes[..bytes.len() / 2]).unwrap();
+    assert_eq!(resume(&dir).map(|(world, _)| world.epoch), Some(10));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
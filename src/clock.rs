@@ -0,0 +1,90 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{cellular_automata::World, font};
+
+/// The time of day written into the world in live cells, again every minute
+/// so the rule can wear it away in between.
+pub struct Clock {
+    twelve_hour: bool,
+    size: Option<usize>,
+    at: Option<(usize, usize)>,
+    /// Minutes since the Unix epoch of the time last stamped.
+    stamped: Option<u64>,
+}
+
+impl Clock {
+    pub fn new(twelve_hour: bool, size: Option<usize>, at: Option<(usize, usize)>) -> Clock {
+        Clock {
+            twelve_hour,
+            size,
+            at,
+            stamped: None,
+        }
+    }
+
+    /// Stamps the time when the minute has changed since the last stamp.
+    pub fn tick(&mut self, world: &mut World) -> Result<(), String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.stamp(world, now)
+    }
+
+    /// Stamps the time `now` seconds after the Unix epoch, unless its minute
+    /// already was.
+    fn stamp(&mut self, world: &mut World, now: u64) -> Result<(), String> {
+        if self.stamped == Some(now / 60) {
+            return Ok(());
+        }
+        self.stamped = Some(now / 60);
+        let (hour, minute) = local_time(now);
+        let text = text(hour, minute, self.twelve_hour);
+        let size = self.size.unwrap_or_else(|| font::fit(&text, world.width, world.height));
+        let pattern = font::text_pattern(&text, size, world.width, world.height)?;
+        let (x, y) = self.at.unwrap_or((
+            world.width.saturating_sub(pattern.width) / 2,
+            world.height.saturating_sub(pattern.height) / 2,
+        ));
+        world.stamp(&pattern, x, y)
+    }
+}
+
+/// Hour and minute of the local time `seconds` after the Unix epoch.
+fn local_time(seconds: u64) -> (u32, u32) {
+    let time = seconds as libc::time_t;
+    // SAFETY: localtime_r only writes into `tm`, which is plain old data.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return ((seconds / 3600 % 24) as u32, (seconds / 60 % 60) as u32);
+    }
+    (tm.tm_hour as u32, tm.tm_min as u32)
+}
+
+/// `13:05`, or `1:05` over `PM` on a twelve hour clock.
+fn text(hour: u32, minute: u32, twelve_hour: bool) -> String {
+    if !twelve_hour {
+        return format!("{:02}:{:02}", hour, minute);
+    }
+    let suffix = if hour < 12 { "AM" } else { "PM" };
+    format!("{}:{:02}\n{}", (hour + 11) % 12 + 1, minute, suffix)
+}
+
+#[test]
+fn test_clock() {
+    use crate::cellular_automata::Rules;
+
+    assert_eq!(text(13, 5, false), "13:05");
+    assert_eq!(text(13, 5, true), "1:05\nPM");
+    assert_eq!(text(0, 30, true), "12:30\nAM");
+    assert_eq!(text(12, 0, true), "12:00\nPM");
+
+    // Stamped once a minute, on top of whatever the rule made of the last one.
+    let mut world = World::new(Rules::Conway, 30, 9, 100, 0.0);
+    let mut clock = Clock::new(false, None, None);
+    let live = |world: &World| world.cells.iter().filter(|cell| cell.is_alive).count();
+    clock.stamp(&mut world, 6000).unwrap();
+    assert!(live(&world) > 0);
+    world.cells.iter_mut().for_each(|cell| cell.is_alive = false);
+    clock.stamp(&mut world, 6059).unwrap();
+    assert_eq!(live(&world), 0);
+    clock.stamp(&mut world, 6060).unwrap();
+    assert!(live(&world) > 0);
+}
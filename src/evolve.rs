@@ -0,0 +1,334 @@
+use std::{io::Write, path::Path};
+
+use clap::ValueEnum;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::{
+    cellular_automata::{
+        generations::GenerationsRule,
+        history::History,
+        ltl::LtlRule,
+        objects::{self, Kind},
+        rulestring::RuleString,
+        stagnation::Detector,
+        Rules, World,
+    },
+    settings::{EvolveSettings, FitnessKind, GenomeFamily},
+    Config,
+};
+
+/// Generations between two `Fitness::sample` calls.
+const SAMPLE_EVERY: u64 = 50;
+
+/// What makes a rule fit, scored soup by soup.
+pub trait Fitness: Sync {
+    /// Called every `SAMPLE_EVERY` generations while a soup runs, the best
+    /// value is handed to `score`.
+    fn sample(&self, _world: &World) -> f64 {
+        0.0
+    }
+
+    /// Score of a soup that settled after `settled` generations, `None` if it
+    /// did not within the run.
+    fn score(&self, world: &World, settled: Option<u64>, best_sample: f64) -> f64;
+}
+
+/// Long but finite transients, as methuselahs have.
+pub struct LongestTransient;
+
+impl Fitness for LongestTransient {
+    fn score(&self, _world: &World, settled: Option<u64>, _best_sample: f64) -> f64 {
+        settled.unwrap_or(0) as f64
+    }
+}
+
+/// Most spaceships flying at once. Only life-like rules' objects are recognised.
+pub struct MostGliders;
+
+impl Fitness for MostGliders {
+    fn sample(&self, world: &World) -> f64 {
+        objects::analyse(world).map_or(0, |found| {
+            found.iter().filter(|o| matches!(o.kind, Kind::Spaceship { .. })).count()
+        }) as f64
+    }
+
+    fn score(&self, world: &World, _settled: Option<u64>, best_sample: f64) -> f64 {
+        best_sample.max(self.sample(world))
+    }
+}
+
+/// Final density as close as possible to a target.
+pub struct TargetDensity(pub f64);
+
+impl Fitness for TargetDensity {
+    fn score(&self, world: &World, _settled: Option<u64>, _best_sample: f64) -> f64 {
+        1.0 - (world.stats().density - self.0).abs()
+    }
+}
+
+pub fn fitness(settings: &EvolveSettings) -> Box<dyn Fitness> {
+    match settings.fitness {
+        FitnessKind::Transient => Box::new(LongestTransient),
+        FitnessKind::Gliders => Box::new(MostGliders),
+        FitnessKind::Density => Box::new(TargetDensity(settings.target_density)),
+    }
+}
+
+/// Evolves a population of rules for `--rounds` rounds and prints the fittest.
+/// Returns the best one.
+pub fn run(config: &Config, settings: &EvolveSettings) -> Option<Rules> {
+    if settings.fitness == FitnessKind::Gliders && settings.family != GenomeFamily::Life {
+        eprintln!("gliders are only recognised under life-like rules");
+        std::process::exit(1);
+    }
+    let fitness = fitness(settings);
+    let checkpoint = settings.checkpoint.as_deref();
+    let (seed, mut round, mut population) = match checkpoint.filter(|path| path.exists()) {
+        Some(path) => load(path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(1);
+        }),
+        None => {
+            let seed = config.0.seed.unwrap_or_else(rand::random);
+            let mut rng = StdRng::seed_from_u64(seed);
+            let population = (0..settings.population).map(|_| random(settings.family, &mut rng)).collect();
+            (seed, 0, population)
+        }
+    };
+
+    while round < settings.rounds {
+        let scores = evaluate(&population, fitness.as_ref(), config, settings, seed.wrapping_add(round));
+        let best = (0..scores.len()).max_by(|&a, &b| scores[a].total_cmp(&scores[b]))?;
+        println!(
+            "round {:>4}  best {:>10.3}  mean {:>10.3}  {}",
+            round + 1,
+            scores[best],
+            scores.iter().sum::<f64>() / scores.len() as f64,
+            population[best]
+        );
+
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(round).rotate_left(32));
+        let tournament = |rng: &mut StdRng| {
+            (0..settings.tournament.max(1))
+                .map(|_| rng.gen_range(0..population.len()))
+                .max_by(|&a, &b| scores[a].total_cmp(&scores[b]))
+                .unwrap()
+        };
+        // The best rule is always kept as it is.
+        let mut next = vec![population[best].clone()];
+        while next.len() < population.len() {
+            let a = &population[tournament(&mut rng)];
+            let child = if rng.gen_bool(settings.crossover) {
+                crossover(a, &population[tournament(&mut rng)], &mut rng)
+            } else {
+                a.clone()
+            };
+            next.push(mutate(&child, settings.mutation, &mut rng));
+        }
+        population = next;
+        round += 1;
+
+        if let Some(path) = checkpoint {
+            if let Err(e) = save(path, seed, round, &population) {
+                eprintln!("{}: {}", path.display(), e);
+            }
+        }
+    }
+
+    let scores = evaluate(&population, fitness.as_ref(), config, settings, seed.wrapping_add(round));
+    let mut ranked: Vec<(f64, &Rules)> = scores.into_iter().zip(&population).collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+    ranked.dedup_by(|a, b| a.1 == b.1);
+    println!("fittest after {} rounds from seed {}:", round, seed);
+    for (score, rule) in ranked.iter().take(5) {
+        println!("{:>10.3}  {}", score, rule);
+    }
+    ranked.first().map(|(_, rule)| (*rule).clone())
+}
+
+/// Scores every rule on the same soups, so that they compete fairly.
+fn evaluate(population: &[Rules], fitness: &dyn Fitness, config: &Config, settings: &EvolveSettings, seed: u64) -> Vec<f64> {
+    population
+        .par_iter()
+        .map(|rule| {
+            let total: f64 = (0..settings.soups)
+                .map(|soup| {
+                    let mut world = World::new(rule.clone(), settings.size, settings.size, 0, 0.35);
+                    world.seed(seed.wrapping_mul(1000).wrapping_add(soup));
+                    world.history = History::new(0);
+                    world.detector = Detector::new(None, 0, config.0.max_period);
+                    world.populate();
+                    let mut best_sample = 0.0f64;
+                    while world.stagnation().is_none() && world.epoch < settings.generations {
+                        world.tick();
+                        if world.epoch.is_multiple_of(SAMPLE_EVERY) {
+                            best_sample = best_sample.max(fitness.sample(&world));
+                        }
+                    }
+                    let settled = world.stagnation().map(|_| world.epoch);
+                    fitness.score(&world, settled, best_sample)
+                })
+                .sum();
+            total / settings.soups.max(1) as f64
+        })
+        .collect()
+}
+
+fn random(family: GenomeFamily, rng: &mut StdRng) -> Rules {
+    let RuleString { birth, survival } = RuleString::random(rng);
+    match family {
+        GenomeFamily::Life => Rules::LifeLike(RuleString { birth, survival }),
+        GenomeFamily::Generations => Rules::Generations(GenerationsRule { birth, survival, states: rng.gen_range(3..=8) }),
+        GenomeFamily::Ltl => {
+            let range = rng.gen_range(1..=5);
+            let mut bounds = || {
+                let (a, b) = (rng.gen_range(1..=neighborhood(range)), rng.gen_range(1..=neighborhood(range)));
+                (a.min(b), a.max(b))
+            };
+            Rules::LargerThanLife(LtlRule {
+                range,
+                states: 2,
+                middle: true,
+                survival: bounds(),
+                birth: bounds(),
+                moore: true,
+            })
+        }
+    }
+}
+
+/// Cells in a Moore neighbourhood of the given range, the middle one included.
+fn neighborhood(range: u8) -> u16 {
+    (2 * range as u16 + 1).pow(2)
+}
+
+/// Each gene comes from either parent.
+fn crossover(a: &Rules, b: &Rules, rng: &mut StdRng) -> Rules {
+    let mut mix = |x: u32, y: u32| {
+        let mask: u32 = rng.gen();
+        x & mask | y & !mask
+    };
+    match (a, b) {
+        (Rules::LifeLike(x), Rules::LifeLike(y)) => Rules::LifeLike(RuleString {
+            birth: mix(x.birth, y.birth),
+            survival: mix(x.survival, y.survival),
+        }),
+        (Rules::Generations(x), Rules::Generations(y)) => Rules::Generations(GenerationsRule {
+            birth: mix(x.birth, y.birth),
+            survival: mix(x.survival, y.survival),
+            states: if rng.gen() { x.states } else { y.states },
+        }),
+        (Rules::LargerThanLife(x), Rules::LargerThanLife(y)) => {
+            Rules::LargerThanLife(fit(LtlRule {
+                range: if rng.gen() { x.range } else { y.range },
+                survival: if rng.gen() { x.survival } else { y.survival },
+                birth: if rng.gen() { x.birth } else { y.birth },
+                ..*x
+            }))
+        }
+        _ => a.clone(),
+    }
+}
+
+/// Flips each bit, or nudges each number, with probability `rate`.
+fn mutate(rule: &Rules, rate: f64, rng: &mut StdRng) -> Rules {
+    let mut flip = |mask: u32, bits: u32| (0..bits).fold(mask, |mask, bit| if rng.gen_bool(rate) { mask ^ 1 << bit } else { mask });
+    match rule {
+        Rules::LifeLike(rule) => Rules::LifeLike(RuleString {
+            birth: flip(rule.birth, 9) & !1,
+            survival: flip(rule.survival, 9),
+        }),
+        Rules::Generations(rule) => {
+            let (birth, survival) = (flip(rule.birth, 9) & !1, flip(rule.survival, 9));
+            let states = match rng.gen_bool(rate) {
+                true if rng.gen() => rule.states.saturating_add(1).min(32),
+                true => rule.states.saturating_sub(1).max(3),
+                false => rule.states,
+            };
+            Rules::Generations(GenerationsRule { birth, survival, states })
+        }
+        Rules::LargerThanLife(rule) => {
+            let mut rule = *rule;
+            if rng.gen_bool(rate) {
+                rule.range = (rule.range as i16 + if rng.gen() { 1 } else { -1 }).clamp(1, 5) as u8;
+            }
+            let step = (neighborhood(rule.range) / 10).max(1) as i32;
+            let mut nudge = |n: u16| {
+                if rng.gen_bool(rate) {
+                    (n as i32 + rng.gen_range(-step..=step)).max(0) as u16
+                } else {
+                    n
+                }
+            };
+            rule.survival = (nudge(rule.survival.0), nudge(rule.survival.1));
+            rule.birth = (nudge(rule.birth.0), nudge(rule.birth.1));
+            Rules::LargerThanLife(fit(rule))
+        }
+        rule => rule.clone(),
+    }
+}
+
+/// Keeps bounds ordered, within the neighbourhood and births above zero.
+fn fit(mut rule: LtlRule) -> LtlRule {
+    let max = neighborhood(rule.range);
+    let order = |(a, b): (u16, u16), min: u16| (a.min(b).clamp(min, max), a.max(b).clamp(min, max));
+    rule.survival = order(rule.survival, 0);
+    rule.birth = order(rule.birth, 1);
+    rule
+}
+
+/// Writes the population as one rule per line, after a header with the seed
+/// and the number of rounds done, through a temporary file so that an
+/// interrupted write leaves the previous checkpoint intact.
+fn save(path: &Path, seed: u64, round: u64, population: &[Rules]) -> std::io::Result<()> {
+    let temporary = path.with_extension("tmp");
+    let mut file = std::io::BufWriter::new(std::fs::File::create(&temporary)?);
+    writeln!(file, "# seed {} round {}", seed, round)?;
+    for rule in population {
+        writeln!(file, "{}", rule)?;
+    }
+    file.flush()?;
+    drop(file);
+    std::fs::rename(temporary, path)
+}
+
+fn load(path: &Path) -> Result<(u64, u64, Vec<Rules>), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut lines = text.lines();
+    let header: Vec<&str> = lines.next().unwrap_or_default().split_whitespace().collect();
+    let ["#", "seed", seed, "round", round] = header.as_slice() else {
+        return Err("not an evolve checkpoint".to_string());
+    };
+    let seed = seed.parse().map_err(|_| format!("bad seed {}", seed))?;
+    let round = round.parse().map_err(|_| format!("bad round {}", round))?;
+    let population = lines
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Rules::from_str(line.trim(), false))
+        .collect::<Result<Vec<Rules>, String>>()?;
+    if population.is_empty() {
+        return Err("empty population".to_string());
+    }
+    Ok((seed, round, population))
+}
+
+#[test]
+fn test_offspring() {
+    let mut rng = StdRng::seed_from_u64(1);
+    // Rulestrings of the presets still load as genomes.
+    let highlife = Rules::HighLife.rulestring().unwrap();
+    assert_eq!(Rules::from_str("B36/S23", false), Ok(Rules::LifeLike(highlife)));
+    for family in [GenomeFamily::Life, GenomeFamily::Generations, GenomeFamily::Ltl] {
+        let (a, b) = (random(family, &mut rng), random(family, &mut rng));
+        let child = mutate(&crossover(&a, &b, &mut rng), 0.5, &mut rng);
+        assert_eq!(Rules::from_str(&child.to_string(), false), Ok(child.clone()));
+        match child {
+            Rules::LifeLike(rule) => assert_eq!(rule.birth & 1, 0),
+            Rules::Generations(rule) => assert!(rule.states >= 3 && rule.birth & 1 == 0),
+            Rules::LargerThanLife(rule) => {
+                assert!(rule.birth.0 >= 1 && rule.birth.0 <= rule.birth.1 && rule.birth.1 <= neighborhood(rule.range))
+            }
+            rule => panic!("{} is not a {:?} rule", rule, family),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::{
+    cellular_automata::{history::History, rulestring::RuleString, stagnation::Detector, stagnation::Stagnation, Rules, World},
+    settings::{ExploreSettings, RuleFamily},
+    Config,
+};
+
+/// Behaviour of a rule on random soups, roughly Wolfram's four classes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Behaviour {
+    Dies,
+    Stable,
+    Periodic,
+    Chaotic,
+    Complex,
+}
+
+impl std::fmt::Display for Behaviour {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Behaviour::Dies => write!(f, "dies (I)"),
+            Behaviour::Stable => write!(f, "stable (II)"),
+            Behaviour::Periodic => write!(f, "periodic (II)"),
+            Behaviour::Chaotic => write!(f, "chaotic (III)"),
+            Behaviour::Complex => write!(f, "complex (IV)"),
+        }
+    }
+}
+
+/// Measurements of one rule, averaged over its soups.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub rule: Rules,
+    pub behaviour: Behaviour,
+    /// Share of live cells at the end of the run.
+    pub density: f64,
+    /// Entropy of the 2x2 blocks of the final world, in `[0, 1]`.
+    pub entropy: f64,
+    /// Share of the cells changing each generation, over the last ones.
+    pub change_rate: f64,
+    /// Generations the soups took to settle, the full run if they did not.
+    pub transient: f64,
+}
+
+/// Samples `--samples` random rules, classifies them and prints them from the
+/// most to the least interesting. Returns the most interesting one.
+pub fn run(config: &Config, settings: &ExploreSettings) -> Option<Rules> {
+    let seed = config.0.seed.unwrap_or_else(rand::random);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut rules = BTreeSet::new();
+    let space = match settings.family {
+        RuleFamily::Life => 1 << 17,
+        RuleFamily::Elementary => 256,
+    };
+    while rules.len() < settings.samples.min(space) {
+        rules.insert(match settings.family {
+            RuleFamily::Life => {
+                let rule = RuleString::random(&mut rng);
+                (rule.birth, rule.survival)
+            }
+            RuleFamily::Elementary => (rng.gen_range(0..256), 0),
+        });
+    }
+
+    let mut samples: Vec<Sample> = rules
+        .into_par_iter()
+        .map(|(birth, survival)| {
+            let rule = match settings.family {
+                RuleFamily::Life => Rules::LifeLike(RuleString { birth, survival }),
+                RuleFamily::Elementary => Rules::Elementary(birth as u8),
+            };
+            measure(rule, config, settings, seed)
+        })
+        .collect();
+    samples.sort_by(|a, b| {
+        b.behaviour
+            .cmp(&a.behaviour)
+            .then_with(|| b.entropy.total_cmp(&a.entropy))
+            .then_with(|| b.transient.total_cmp(&a.transient))
+    });
+
+    println!("{:>4}  {:<20} {:<14} {:>8} {:>8} {:>8} {:>10}", "rank", "rule", "behaviour", "density", "entropy", "change", "transient");
+    for (rank, sample) in samples.iter().enumerate().take(settings.top) {
+        println!(
+            "{:>4}  {:<20} {:<14} {:>7.1}% {:>8.3} {:>7.1}% {:>10.0}",
+            rank + 1,
+            sample.rule.to_string(),
+            sample.behaviour.to_string(),
+            sample.density * 100.0,
+            sample.entropy,
+            sample.change_rate * 100.0,
+            sample.transient
+        );
+    }
+    println!("{} rules from seed {}", samples.len(), seed);
+    samples.into_iter().next().map(|sample| sample.rule)
+}
+
+fn measure(rule: Rules, config: &Config, settings: &ExploreSettings, seed: u64) -> Sample {
+    let mut behaviours = Vec::new();
+    let (mut density, mut entropy, mut change_rate, mut transient) = (0.0, 0.0, 0.0, 0.0);
+    for soup in 0..settings.soups {
+        let mut world = World::new(rule.clone(), settings.size, settings.size, 0, 0.35);
+        world.seed(seed.wrapping_add(soup));
+        world.history = History::new(0);
+        world.detector = Detector::new(None, 0, config.0.max_period);
+        world.populate();
+
+        let mut changes = Vec::new();
+        let mut rows = VecDeque::new();
+        let mut shifting_since = None;
+        while world.stagnation().is_none() && world.epoch < settings.generations {
+            let previous = world.cells.iter().map(|c| c.is_alive).collect::<Vec<bool>>();
+            world.tick();
+            changes.push(changed(&world, &previous));
+            if let Rules::Elementary(_) = world.rule {
+                if shifting_since.is_none() && repeats_shifted(&mut rows, &world, config.0.max_period) {
+                    shifting_since = Some(world.epoch);
+                }
+            }
+        }
+        let settled_at = match world.stagnation() {
+            Some(_) => world.epoch,
+            None => shifting_since.unwrap_or(world.epoch),
+        };
+        let recent = &changes[changes.len().saturating_sub(100)..];
+        let soup_change_rate = recent.iter().sum::<f64>() / recent.len().max(1) as f64;
+        let soup_entropy = block_entropy(&world);
+
+        // Small worlds end up cycling whatever the rule: a cycle that still
+        // looks random is chaos, and only soups settling early count as
+        // stable or periodic.
+        let settled_early = settled_at * 4 <= settings.generations;
+        behaviours.push(match world.stagnation() {
+            Some(Stagnation::Extinct) => Behaviour::Dies,
+            _ if soup_change_rate > 0.05 && soup_entropy > 0.78 => Behaviour::Chaotic,
+            Some(Stagnation::Periodic(1)) if settled_early => Behaviour::Stable,
+            _ if settled_early && (world.stagnation().is_some() || shifting_since.is_some()) => Behaviour::Periodic,
+            _ => Behaviour::Complex,
+        });
+        density += world.stats().density;
+        entropy += soup_entropy;
+        change_rate += soup_change_rate;
+        transient += settled_at as f64;
+    }
+
+    // The behaviour most of the soups showed, the more interesting one on ties.
+    let behaviour = behaviours
+        .iter()
+        .copied()
+        .max_by_key(|b| (behaviours.iter().filter(|other| *other == b).count(), *b))
+        .unwrap_or(Behaviour::Dies);
+    let soups = settings.soups.max(1) as f64;
+    Sample {
+        rule,
+        behaviour,
+        density: density / soups,
+        entropy: entropy / soups,
+        change_rate: change_rate / soups,
+        transient: transient / soups,
+    }
+}
+
+/// Share of the cells that changed during the last tick. Elementary rules only
+/// change their bottom row, the rest of the world being their past.
+fn changed(world: &World, previous: &[bool]) -> f64 {
+    let cells: Vec<bool> = world.cells.iter().map(|c| c.is_alive).collect();
+    let (current, previous) = match world.rule {
+        Rules::Elementary(_) => {
+            let bottom = cells.len() - world.width;
+            (&cells[bottom..], &previous[bottom..])
+        }
+        _ => (&cells[..], previous),
+    };
+    current.iter().zip(previous).filter(|(a, b)| a != b).count() as f64 / current.len().max(1) as f64
+}
+
+/// Whether the bottom row of an elementary world is one of the last rows
+/// rotated, which the world-wide cycle detection misses.
+fn repeats_shifted(rows: &mut VecDeque<Vec<bool>>, world: &World, max_period: usize) -> bool {
+    let row: Vec<bool> = world.cells[world.cells.len() - world.width..].iter().map(|c| c.is_alive).collect();
+    let repeats = rows.iter().any(|past: &Vec<bool>| {
+        (0..row.len()).any(|shift| row.iter().enumerate().all(|(x, &alive)| past[(x + shift) % row.len()] == alive))
+    });
+    rows.push_back(row);
+    if rows.len() > max_period {
+        rows.pop_front();
+    }
+    repeats
+}
+
+/// Shannon entropy of the overlapping 2x2 blocks of cells, scaled to `[0, 1]`.
+fn block_entropy(world: &World) -> f64 {
+    let alive = |x: usize, y: usize| world.cells[y * world.width + x].is_alive as usize;
+    let mut counts = [0usize; 16];
+    for y in 0..world.height.saturating_sub(1) {
+        for x in 0..world.width.saturating_sub(1) {
+            counts[alive(x, y) | alive(x + 1, y) << 1 | alive(x, y + 1) << 2 | alive(x + 1, y + 1) << 3] += 1;
+        }
+    }
+    let total = counts.iter().sum::<usize>().max(1) as f64;
+    -counts
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| n as f64 / total * (n as f64 / total).log2())
+        .sum::<f64>()
+        / 4.0
+}
+
+#[test]
+fn test_classify() {
+    let config = Config(clap::Parser::parse_from(["test"]));
+    let settings = ExploreSettings {
+        family: RuleFamily::Elementary,
+        samples: 0,
+        soups: 2,
+        size: 64,
+        generations: 200,
+        top: 0,
+        play: false,
+    };
+    assert_eq!(measure(Rules::Elementary(0), &config, &settings, 1).behaviour, Behaviour::Dies);
+    assert_eq!(measure(Rules::Elementary(30), &config, &settings, 1).behaviour, Behaviour::Chaotic);
+    assert_eq!(measure(Rules::Elementary(170), &config, &settings, 1).behaviour, Behaviour::Periodic);
+}
@@ -0,0 +1,328 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cellular_automata::World,
+    render::{self, Frame},
+    settings::StreamFormat,
+};
+
+/// Writes `frame` as an RGBA PNG.
+pub fn write_png(path: &Path, frame: &Frame) -> std::io::Result<()> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, frame.width as u32, frame.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&frame.pixels)?;
+    Ok(writer.finish()?)
+}
+
+/// Every `every`th generation written to a directory as PNG, `scale`
+/// pixels per cell.
+pub struct PngFrames {
+    dir: PathBuf,
+    every: u64,
+    scale: usize,
+    grad: colorgrad::Gradient,
+}
+
+impl PngFrames {
+    pub fn new(dir: &Path, every: u64, scale: usize, world: &World) -> std::io::Result<PngFrames> {
+        std::fs::create_dir_all(dir)?;
+        Ok(PngFrames {
+            dir: dir.to_path_buf(),
+            every: every.max(1),
+            scale: scale.max(1),
+            grad: render::gradient(&world.rule),
+        })
+    }
+
+    pub fn tick(&self, world: &World) -> std::io::Result<()> {
+        if !world.epoch.is_multiple_of(self.every) {
+            return Ok(());
+        }
+        let frame = render::render(world, &self.grad, world.width * self.scale, world.height * self.scale);
+        write_png(&self.dir.join(format!("{:08}.png", world.epoch)), &frame)
+    }
+}
+
+/// The world recorded as an animated GIF, one frame every `every`
+/// generations until `frames` are captured. Frames only store the rectangle
+/// that changed, and identical ones lengthen the previous frame instead.
+pub struct GifRecorder {
+    encoder: Option<gif::Encoder<BufWriter<File>>>,
+    frames: usize,
+    every: u64,
+    scale: usize,
+    /// Hundredths of a second each frame is shown.
+    delay: u16,
+    grad: colorgrad::Gradient,
+    palette: Vec<[u8; 3]>,
+    indices: HashMap<[u8; 4], u8>,
+    previous: Vec<u8>,
+    pending: Option<gif::Frame<'static>>,
+}
+
+impl GifRecorder {
+    /// Starts the recording with the world as it is now.
+    pub fn new(path: &Path, frames: usize, every: u64, scale: usize, tbt: u64, world: &World) -> Result<GifRecorder, String> {
+        let scale = scale.max(1);
+        let (width, height) = (world.width * scale, world.height * scale);
+        let too_big = || format!("a {}x{} GIF is too big, lower --scale", width, height);
+        let (gif_width, gif_height) = (u16::try_from(width).map_err(|_| too_big())?, u16::try_from(height).map_err(|_| too_big())?);
+        let grad = render::gradient(&world.rule);
+        let palette = render::palette(world, &grad);
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut encoder = gif::Encoder::new(BufWriter::new(file), gif_width, gif_height, &palette.concat())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut recorder = GifRecorder {
+            encoder: Some(encoder),
+            frames,
+            every: every.max(1),
+            scale,
+            delay: (tbt * every.max(1) / 10).clamp(2, u16::MAX as u64) as u16,
+            grad,
+            palette,
+            indices: HashMap::new(),
+            previous: Vec::new(),
+            pending: None,
+        };
+        recorder.capture(world).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(recorder)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.encoder.is_none()
+    }
+
+    pub fn tick(&mut self, world: &World) -> Result<(), gif::EncodingError> {
+        if self.is_finished() || !world.epoch.is_multiple_of(self.every) {
+            return Ok(());
+        }
+        self.capture(world)
+    }
+
+    fn capture(&mut self, world: &World) -> Result<(), gif::EncodingError> {
+        let frame = render::render(world, &self.grad, world.width * self.scale, world.height * self.scale);
+        let pixels: Vec<u8> = frame.pixels.chunks_exact(4).map(|rgba| self.index(rgba)).collect();
+
+        let changed = (0..pixels.len()).filter(|&i| self.previous.get(i) != Some(&pixels[i]));
+        let (mut x0, mut y0, mut x1, mut y1) = (usize::MAX, usize::MAX, 0, 0);
+        for i in changed {
+            let (x, y) = (i % frame.width, i / frame.width);
+            (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+        }
+        if x0 > x1 {
+            if let Some(pending) = &mut self.pending {
+                pending.delay = pending.delay.saturating_add(self.delay);
+            }
+        } else {
+            let buffer = (y0..=y1).flat_map(|y| pixels[y * frame.width + x0..=y * frame.width + x1].to_vec()).collect::<Vec<u8>>();
+            let next = gif::Frame {
+                left: x0 as u16,
+                top: y0 as u16,
+                width: (x1 - x0 + 1) as u16,
+                height: (y1 - y0 + 1) as u16,
+                delay: self.delay,
+                dispose: gif::DisposalMethod::Keep,
+                buffer: buffer.into(),
+                ..gif::Frame::default()
+            };
+            self.flush(Some(next))?;
+        }
+        self.previous = pixels;
+
+        self.frames = self.frames.saturating_sub(1);
+        if self.frames == 0 {
+            self.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Palette index of the closest colour.
+    fn index(&mut self, rgba: &[u8]) -> u8 {
+        let palette = &self.palette;
+        *self.indices.entry([rgba[0], rgba[1], rgba[2], rgba[3]]).or_insert_with(|| {
+            let distance = |color: &[u8; 3]| (0..3).map(|c| (color[c] as i32 - rgba[c] as i32).pow(2)).sum::<i32>();
+            (0..palette.len()).min_by_key(|&i| distance(&palette[i])).unwrap_or(0) as u8
+        })
+    }
+
+    /// Writes the pending frame, keeping `next` back in case the frames after
+    /// it are identical.
+    fn flush(&mut self, next: Option<gif::Frame<'static>>) -> Result<(), gif::EncodingError> {
+        if let (Some(encoder), Some(pending)) = (&mut self.encoder, &self.pending) {
+            encoder.write_frame(pending)?;
+        }
+        self.pending = next;
+        Ok(())
+    }
+
+    /// Writes the last frame and the GIF trailer.
+    fn finish(&mut self) -> Result<(), gif::EncodingError> {
+        self.flush(None)?;
+        self.encoder = None;
+        Ok(())
+    }
+}
+
+impl Drop for GifRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            eprintln!("gif: {}", e);
+        }
+    }
+}
+
+/// Every generation written as a raw video frame, one at a time, to a file
+/// or stdout for an encoder to read.
+pub struct VideoStream {
+    out: BufWriter<Box<dyn Write + Send + Sync>>,
+    format: StreamFormat,
+    scale: usize,
+    grad: colorgrad::Gradient,
+}
+
+impl VideoStream {
+    /// Writes the header, if the format has one, and the world as it is now.
+    /// `fps` defaults to one frame every `tbt` milliseconds.
+    pub fn new(path: &Path, format: Option<StreamFormat>, scale: usize, fps: Option<u64>, tbt: u64, world: &World) -> std::io::Result<VideoStream> {
+        let out: Box<dyn Write + Send + Sync> = match path.to_str() {
+            Some("-") => Box::new(std::io::stdout()),
+            _ => Box::new(File::create(path)?),
+        };
+        let format = format.unwrap_or(match path.extension().and_then(|e| e.to_str()) {
+            Some("ppm") => StreamFormat::Ppm,
+            _ => StreamFormat::Y4m,
+        });
+        let mut stream = VideoStream {
+            out: BufWriter::new(out),
+            format,
+            scale: scale.max(1),
+            grad: render::gradient(&world.rule),
+        };
+        if format == StreamFormat::Y4m {
+            let (width, height) = (world.width * stream.scale, world.height * stream.scale);
+            let (numerator, denominator) = match fps {
+                Some(fps) => (fps.max(1), 1),
+                None => (1000, tbt.max(1)),
+            };
+            writeln!(stream.out, "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444", width, height, numerator, denominator)?;
+        }
+        stream.tick(world)?;
+        Ok(stream)
+    }
+
+    pub fn tick(&mut self, world: &World) -> std::io::Result<()> {
+        let frame = render::render(world, &self.grad, world.width * self.scale, world.height * self.scale);
+        match self.format {
+            StreamFormat::Y4m => {
+                self.out.write_all(b"FRAME\n")?;
+                for plane in 0..3 {
+                    let bytes: Vec<u8> = frame.pixels.chunks_exact(4).map(|rgba| ycbcr(rgba)[plane]).collect();
+                    self.out.write_all(&bytes)?;
+                }
+            }
+            StreamFormat::Ppm => {
+                write!(self.out, "P6\n{} {}\n255\n", frame.width, frame.height)?;
+                let bytes: Vec<u8> = frame.pixels.chunks_exact(4).flat_map(|rgba| [rgba[0], rgba[1], rgba[2]]).collect();
+                self.out.write_all(&bytes)?;
+            }
+        }
+        self.out.flush()
+    }
+}
+
+/// Studio range BT.601 `[Y, Cb, Cr]`, what YUV4MPEG2 readers expect.
+fn ycbcr(rgba: &[u8]) -> [u8; 3] {
+    let (r, g, b) = (rgba[0] as f32, rgba[1] as f32, rgba[2] as f32);
+    [
+        16.0 + (65.738 * r + 129.057 * g + 25.064 * b) / 256.0,
+        128.0 + (-37.945 * r - 74.494 * g + 112.439 * b) / 256.0,
+        128.0 + (112.439 * r - 94.154 * g - 18.285 * b) / 256.0,
+    ]
+    .map(|c| c.round().clamp(0.0, 255.0) as u8)
+}
+
+#[test]
+fn test_png() {
+    use crate::cellular_automata::Rules;
+
+    let mut world = World::new(Rules::Conway, 12, 8, 100, 0.4);
+    world.seed(1);
+    world.populate();
+    world.tick();
+    let dir = std::env::temp_dir().join(format!("png-{}", std::process::id()));
+    PngFrames::new(&dir, 1, 3, &world).unwrap().tick(&world).unwrap();
+
+    let decoder = png::Decoder::new(std::fs::File::open(dir.join("00000001.png")).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    let frame = render::render(&world, &render::gradient(&world.rule), 36, 24);
+    assert_eq!((info.width, info.height), (36, 24));
+    assert_eq!(pixels, frame.pixels);
+    let alive = world.cells.iter().position(|cell| cell.is_alive).unwrap();
+    let i = ((alive % 12) * 3 + 1 + ((alive / 12) * 3 + 1) * 36) * 4;
+    assert_eq!(pixels[i..i + 4], render::cell_color(&world, &world.cells[alive], &render::gradient(&world.rule)));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_gif() {
+    use crate::cellular_automata::Rules;
+
+    let mut world = World::new(Rules::Conway, 16, 12, 100, 0.0);
+    for y in 4..7 {
+        world.revive(4, y);
+    }
+    let path = std::env::temp_dir().join(format!("record-{}.gif", std::process::id()));
+    let mut recorder = GifRecorder::new(&path, 4, 1, 2, 100, &world).unwrap();
+    while !recorder.is_finished() {
+        world.tick();
+        recorder.tick(&world).unwrap();
+    }
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (32, 24));
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        frames.push((frame.left, frame.top, frame.width, frame.height, frame.delay));
+    }
+    // A blinker: the whole world, then only the 3x3 cells around it change.
+    assert_eq!(frames, [(0, 0, 32, 24, 10), (6, 8, 6, 6, 10), (6, 8, 6, 6, 10), (6, 8, 6, 6, 10)]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_stream() {
+    use crate::cellular_automata::Rules;
+
+    let mut world = World::new(Rules::Conway, 4, 3, 100, 0.0);
+    world.revive(1, 1);
+    let path = std::env::temp_dir().join(format!("stream-{}.y4m", std::process::id()));
+    let mut stream = VideoStream::new(&path, None, 2, None, 40, &world).unwrap();
+    world.tick();
+    stream.tick(&world).unwrap();
+    drop(stream);
+
+    let bytes = std::fs::read(&path).unwrap();
+    let header = b"YUV4MPEG2 W8 H6 F1000:40 Ip A1:1 C444\n";
+    assert!(bytes.starts_with(header));
+    let frame = 6 + 8 * 6 * 3;
+    assert_eq!(bytes.len(), header.len() + 2 * frame);
+    // The live cell's pixels in the first frame, dead and black in the second.
+    let first = &bytes[header.len() + 6..];
+    assert_eq!((first[0], first[48], first[96]), (16, 128, 128));
+    assert!(first[2 + 2 * 8] > 100);
+    assert_eq!(bytes[header.len() + frame + 6 + 2 + 2 * 8], 16);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,144 @@
+use crate::{
+    canvas::{Canvas, Pixel},
+    cellular_automata::pattern::Pattern,
+};
+
+pub const WIDTH: usize = 5;
+pub const HEIGHT: usize = 7;
+
+/// A 5x7 font with the printable ASCII characters most messages need.
+/// Lower case letters are drawn as upper case.
+#[rustfmt::skip]
+const GLYPHS: &[(char, [&str; HEIGHT])] = &[
+    (' ', [".....", ".....", ".....", ".....", ".....", ".....", "....."]),
+    ('!', ["..#..", "..#..", "..#..", "..#..", "..#..", ".....", "..#.."]),
+    ('"', [".#.#.", ".#.#.", ".#.#.", ".....", ".....", ".....", "....."]),
+    ('#', [".#.#.", ".#.#.", "#####", ".#.#.", "#####", ".#.#.", ".#.#."]),
+    ('%', ["##...", "##..#", "...#.", "..#..", ".#...", "#..##", "...##"]),
+    ('&', [".##..", "#..#.", "#.#..", ".#...", "#.#.#", "#..#.", ".##.#"]),
+    ('\'', ["..#..", "..#..", ".#...", ".....", ".....", ".....", "....."]),
+    ('(', ["...#.", "..#..", ".#...", ".#...", ".#...", "..#..", "...#."]),
+    (')', [".#...", "..#..", "...#.", "...#.", "...#.", "..#..", ".#..."]),
+    ('*', [".....", "..#..", "#.#.#", ".###.", "#.#.#", "..#..", "....."]),
+    ('+', [".....", "..#..", "..#..", "#####", "..#..", "..#..", "....."]),
+    (',', [".....", ".....", ".....", ".....", ".##..", "..#..", ".#..."]),
+    ('-', [".....", ".....", ".....", "#####", ".....", ".....", "....."]),
+    ('.', [".....", ".....", ".....", ".....", ".....", ".##..", ".##.."]),
+    ('/', [".....", "....#", "...#.", "..#..", ".#...", "#....", "....."]),
+    ('0', [".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###."]),
+    ('1', ["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('2', [".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####"]),
+    ('3', ["#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###."]),
+    ('4', ["...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#."]),
+    ('5', ["#####", "#....", "####.", "....#", "....#", "#...#", ".###."]),
+    ('6', ["..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###."]),
+    ('7', ["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#..."]),
+    ('8', [".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###."]),
+    ('9', [".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##.."]),
+    (':', [".....", ".##..", ".##..", ".....", ".##..", ".##..", "....."]),
+    (';', [".....", ".##..", ".##..", ".....", ".##..", "..#..", ".#..."]),
+    ('<', ["...#.", "..#..", ".#...", "#....", ".#...", "..#..", "...#."]),
+    ('=', [".....", ".....", "#####", ".....", "#####", ".....", "....."]),
+    ('>', [".#...", "..#..", "...#.", "....#", "...#.", "..#..", ".#..."]),
+    ('?', [".###.", "#...#", "....#", "...#.", "..#..", ".....", "..#.."]),
+    ('@', [".###.", "#...#", "....#", ".##.#", "#.#.#", "#.#.#", ".###."]),
+    ('A', [".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"]),
+    ('B', ["####.", "#...#", "#...#", "####.", "#...#", "#...#", "####."]),
+    ('C', [".###.", "#...#", "#....", "#....", "#....", "#...#", ".###."]),
+    ('D', ["###..", "#..#.", "#...#", "#...#", "#...#", "#..#.", "###.."]),
+    ('E', ["#####", "#....", "#....", "####.", "#....", "#....", "#####"]),
+    ('F', ["#####", "#....", "#....", "####.", "#....", "#....", "#...."]),
+    ('G', [".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".####"]),
+    ('H', ["#...#", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"]),
+    ('I', [".###.", "..#..", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('J', ["..###", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##.."]),
+    ('K', ["#...#", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "#...#"]),
+    ('L', ["#....", "#....", "#....", "#....", "#....", "#....", "#####"]),
+    ('M', ["#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#"]),
+    ('N', ["#...#", "#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#"]),
+    ('O', [".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."]),
+    ('P', ["####.", "#...#", "#...#", "####.", "#....", "#....", "#...."]),
+    ('Q', [".###.", "#...#", "#...#", "#...#", "#.#.#", "#..#.", ".##.#"]),
+    ('R', ["####.", "#...#", "#...#", "####.", "#.#..", "#..#.", "#...#"]),
+    ('S', [".####", "#....", "#....", ".###.", "....#", "....#", "####."]),
+    ('T', ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."]),
+    ('U', ["#...#", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."]),
+    ('V', ["#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#.."]),
+    ('W', ["#...#", "#...#", "#...#", "#.#.#", "#.#.#", "#.#.#", ".#.#."]),
+    ('X', ["#...#", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "#...#"]),
+    ('Y', ["#...#", "#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["#####", "....#", "...#.", "..#..", ".#...", "#....", "#####"]),
+    ('_', [".....", ".....", ".....", ".....", ".....", ".....", "#####"]),
+];
+
+/// Rows of the glyph for `c`, `?` for the characters the font lacks.
+pub fn glyph(c: char) -> &'static [&'static str; HEIGHT] {
+    let c = c.to_ascii_uppercase();
+    let find = |c| GLYPHS.iter().find(|(glyph, _)| *glyph == c).map(|(_, rows)| rows);
+    find(c).or_else(|| find('?')).unwrap()
+}
+
+/// Size in cells of `text` drawn with each font pixel `size` cells wide,
+/// glyphs and lines one font pixel apart.
+pub fn text_size(text: &str, size: usize) -> (usize, usize) {
+    let columns = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+    let lines = text.lines().count();
+    (
+        (columns * (WIDTH + 1)).saturating_sub(1).saturating_mul(size),
+        (lines * (HEIGHT + 1)).saturating_sub(1).saturating_mul(size),
+    )
+}
+
+/// The largest size `text` fits a `width` x `height` world at, at least 1.
+pub fn fit(text: &str, width: usize, height: usize) -> usize {
+    let (w, h) = text_size(text, 1);
+    (width / w.max(1)).min(height / h.max(1)).max(1)
+}
+
+/// `text` as live cells, each line centred under the longest one. Fails
+/// before drawing anything if it would not fit a `world_width` x
+/// `world_height` world.
+pub fn text_pattern(text: &str, size: usize, world_width: usize, world_height: usize) -> Result<Pattern, String> {
+    let size = size.max(1);
+    let (width, height) = text_size(text, size);
+    if width > world_width || height > world_height {
+        return Err(format!(
+            "the text is {}x{} cells at font size {}, too big for the {}x{} world",
+            width, height, size, world_width, world_height
+        ));
+    }
+    let mut canvas = Canvas::new(width, height);
+    let lit = Pixel { r: 255, g: 255, b: 255, a: 255 };
+    for (row, line) in text.lines().enumerate() {
+        let x = (width - text_size(line, size).0) / 2;
+        canvas.draw_text(x, row * (HEIGHT + 1) * size, line, size, lit);
+    }
+    Ok(Pattern {
+        width,
+        height,
+        cells: canvas.lit().map(|(x, y)| (x, y, 1)).collect(),
+        ..Pattern::default()
+    })
+}
+
+#[test]
+fn test_text_pattern() {
+    let hi = text_pattern("Hi", 1, 11, 7).unwrap();
+    assert_eq!((hi.width, hi.height), (11, 7));
+    // H's crossbar and both of I's serifs on the top row.
+    assert!(hi.cells.contains(&(2, 3, 1)));
+    assert_eq!(hi.cells.iter().filter(|&&(_, y, _)| y == 0).count(), 2 + 3);
+
+    let big = text_pattern("Hi", 3, 40, 30).unwrap();
+    assert_eq!((big.width, big.height), (33, 21));
+    assert_eq!(big.cells.len(), hi.cells.len() * 9);
+
+    // The shorter second line is centred.
+    let lines = text_pattern("HI\n!", 1, 40, 30).unwrap();
+    assert_eq!((lines.width, lines.height), (11, 15));
+    assert!(lines.cells.contains(&(5, 8, 1)));
+    assert_eq!(fit("HI", 40, 30), 3);
+    assert!(text_pattern("Hi", 2, 21, 14).is_err());
+    assert!(text_pattern("Hi", usize::MAX, 30, 30).is_err());
+    assert_eq!(glyph('~'), glyph('?'));
+}
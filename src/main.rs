@@ -3,10 +3,22 @@ extern crate termion;
 
 mod braille;
 mod canvas;
+mod cast;
 mod cellular_automata;
+mod checkpoint;
+mod clock;
+mod evolve;
+mod explore;
+mod export;
+mod font;
+mod render;
+mod search;
+mod seed;
 mod settings;
+mod sparse;
+mod viewer3d;
 
-use std::time::Duration;
+use std::{io::Write, sync::Arc, time::Duration};
 
 use bevy::{
     prelude::*,
@@ -17,7 +29,23 @@ use bevy::{
     window::{WindowResolution, WindowResized},
 };
 
-use cellular_automata::{Rules, World};
+use clap::ValueEnum;
+use termion::{event::Key, input::TermRead, raw::IntoRawMode};
+
+use cellular_automata::{
+    history::History,
+    objects::{self, ObjectCensus},
+    pattern::Pattern,
+    stagnation::{Detector, Stagnation},
+    stats::CsvExport,
+    table::RuleTable,
+    Rules, World,
+};
+use cast::Cast;
+use checkpoint::Checkpoints;
+use clock::Clock;
+use export::{GifRecorder, PngFrames, VideoStream};
+use settings::Command;
 
 #[derive(Resource, Default)]
 struct Config(settings::CommandLineProvidedSettings);
@@ -36,6 +64,78 @@ struct WorldRepr {
 #[derive(Resource)]
 struct WorldState {
     world: World,
+    paused: bool,
+    hud: bool,
+    census: CensusCache,
+    clock: Option<Clock>,
+    exports: Exports,
+}
+
+impl WorldState {
+    fn tick(&mut self) {
+        self.world.tick();
+        tick_clock(&mut self.clock, &mut self.world);
+        self.exports.tick(&self.world);
+    }
+}
+
+/// Re-stamps the clock if the minute has turned, dropping it if it no longer fits.
+fn tick_clock(clock: &mut Option<Clock>, world: &mut World) {
+    if let Some(c) = clock {
+        if let Err(e) = c.tick(world) {
+            eprintln!("clock: {}", e);
+            *clock = None;
+        }
+    }
+}
+
+/// What is written out every generation.
+struct Exports {
+    stats_csv: Option<CsvExport>,
+    checkpoints: Option<Checkpoints>,
+    png_frames: Option<PngFrames>,
+    gif: Option<GifRecorder>,
+    stream: Option<VideoStream>,
+}
+
+impl Exports {
+    fn tick(&mut self, world: &World) {
+        if let Some(csv) = &mut self.stats_csv {
+            if let Err(e) = csv.write(&world.stats()) {
+                eprintln!("stats: {}", e);
+                self.stats_csv = None;
+            }
+        }
+        if let Some(checkpoints) = &self.checkpoints {
+            if let Err(e) = checkpoints.tick(world) {
+                eprintln!("checkpoint: {}", e);
+                self.checkpoints = None;
+            }
+        }
+        if let Some(png_frames) = &self.png_frames {
+            if let Err(e) = png_frames.tick(world) {
+                eprintln!("png: {}", e);
+                self.png_frames = None;
+            }
+        }
+        if let Some(gif) = &mut self.gif {
+            if let Err(e) = gif.tick(world) {
+                eprintln!("gif: {}", e);
+                self.gif = None;
+            }
+        }
+        if let Some(stream) = &mut self.stream {
+            if let Err(e) = stream.tick(world) {
+                eprintln!("stream: {}", e);
+                self.stream = None;
+            }
+        }
+    }
+
+    /// Whether a recording was asked for and is over.
+    fn is_finished(&self) -> bool {
+        self.gif.as_ref().is_some_and(GifRecorder::is_finished)
+    }
 }
 
 #[derive(Resource)]
@@ -47,63 +147,382 @@ fn main() {
     let config = Config::default();
     let tbt = config.0.tbt;
     let scale = config.0.scale;
-    let width = (config.0.width * scale) as f32;
-    let height = (config.0.height * scale) as f32;
-    let dimensions = Dimensions {
-        width: width as u16,
-        height: height as u16,
+    if let Some(rule) = config.0.rules3d {
+        let dimensions = Dimensions {
+            width: (config.0.width * scale) as u16,
+            height: (config.0.height * scale) as u16,
+        };
+        return viewer3d::run(config, dimensions, rule);
+    }
+
+    let mut rules = match &config.0.rule_file {
+        Some(path) => match RuleTable::load(path) {
+            Ok(table) => Rules::Table(Arc::new(table)),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => config.0.rules.clone(),
     };
 
+    match &config.0.command {
+        Some(Command::Search(settings)) => return search::run(&config, rules, settings),
+        Some(Command::Explore(settings)) => match explore::run(&config, settings) {
+            Some(best) if settings.play => rules = best,
+            _ => return,
+        },
+        Some(Command::Evolve(settings)) => match evolve::run(&config, settings) {
+            Some(best) if settings.play => rules = best,
+            _ => return,
+        },
+        None => (),
+    }
+
+    let image = config.0.seed_image.as_ref().map(|path| {
+        seed::Image::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+    let (world_width, world_height) = match &image {
+        Some(image) if config.0.fit_image => (image.width, image.height),
+        _ => (config.0.width, config.0.height),
+    };
+
+    if let Some(path) = &config.0.pattern {
+        match sparse::oversized(path, world_width, world_height) {
+            Ok(Some(macrocell)) => {
+                let rule = match (&macrocell.pattern.rule, &config.0.rule_file) {
+                    (Some(rule), None) => Rules::from_str(rule, false).unwrap_or_else(|e| {
+                        eprintln!("{}, using {} instead", e, rules);
+                        rules
+                    }),
+                    _ => rules,
+                };
+                return sparse::run(config, macrocell, rule);
+            }
+            Ok(None) => (),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let pattern = config.0.pattern.as_ref().map(|path| {
+        Pattern::load(path, world_width, world_height).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+    if let (Some(rule), None) = (pattern.as_ref().and_then(|p| p.rule.as_ref()), &config.0.rule_file) {
+        match Rules::from_str(rule, false) {
+            Ok(rule) => rules = rule,
+            Err(e) => eprintln!("{}, using {} instead", e, rules),
+        }
+    }
     let mut world = World::new(
-        config.0.rules.clone(),
-        config.0.width,
-        config.0.height,
+        rules,
+        world_width,
+        world_height,
         config.0.reset,
         0.2,
     );
+    if world.rule.is_life_like() && config.0.species > 1 {
+        world.species = config.0.species;
+    }
+
+    if let Some(seed) = config.0.seed {
+        world.seed(seed);
+    }
+    world.history = History::new(config.0.history);
+    world.detector = Detector::new(config.0.reset_policy, config.0.reset_grace, config.0.max_period);
+    let (resumed, run) = match (&config.0.checkpoint_dir, config.0.resume) {
+        (Some(dir), true) => checkpoint::resume(dir).or_else(|| {
+            eprintln!("no checkpoint to resume from in {}, starting afresh", dir.display());
+            None
+        }),
+        _ => None,
+    }
+    .map_or((None, None), |(world, run)| (Some(world), Some(run)));
+    let pattern = pattern.or_else(|| {
+        let (threshold, dither, invert) = (config.0.threshold, config.0.dither, config.0.invert);
+        let states = world.rule.states();
+        image.map(|image| image.to_pattern(world.width, world.height, states, threshold, dither, invert))
+    });
+    let pattern = pattern.or_else(|| {
+        let text = config.0.seed_text.as_ref()?;
+        let size = config.0.font_size.unwrap_or_else(|| font::fit(text, world.width, world.height));
+        Some(font::text_pattern(text, size, world.width, world.height).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }))
+    });
+    match (resumed, &config.0.snapshot, &pattern) {
+        (Some(resumed), _, _) => world = resumed,
+        (None, Some(path), _) => {
+            world = World::load_snapshot(path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            })
+        }
+        (None, None, Some(pattern)) => {
+            let (x, y) = config.0.at.unwrap_or((
+                world.width.saturating_sub(pattern.width) / 2,
+                world.height.saturating_sub(pattern.height) / 2,
+            ));
+            if let Err(e) = world.stamp(pattern, x, y) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        // The clock starts from an empty world, like a message would.
+        (None, None, None) if config.0.clock => (),
+        (None, None, None) => world.populate(),
+    }
 
-    world.populate();
-    world.revive(0, 0);
-    world.revive(0, 1);
+    let clock = config.0.clock.then(|| {
+        let mut clock = Clock::new(config.0.twelve_hour, config.0.font_size, config.0.at);
+        if let Err(e) = clock.tick(&mut world) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        clock
+    });
+
+    let stats_csv = config.0.stats_csv.as_ref().map(|path| match CsvExport::create(path) {
+        Ok(csv) => csv,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    });
+
+    let checkpoints = config.0.checkpoint_dir.as_ref().map(|dir| {
+        Checkpoints::new(dir, config.0.checkpoint_every, config.0.keep_checkpoints, run).unwrap_or_else(|e| {
+            eprintln!("{}: {}", dir.display(), e);
+            std::process::exit(1);
+        })
+    });
+    let png_frames = config.0.png_dir.as_ref().map(|dir| {
+        PngFrames::new(dir, config.0.png_every, config.0.scale, &world).unwrap_or_else(|e| {
+            eprintln!("{}: {}", dir.display(), e);
+            std::process::exit(1);
+        })
+    });
+    let gif = config.0.record.as_ref().map(|path| {
+        let (frames, every, scale, tbt) = (config.0.frames, config.0.record_every, config.0.scale, config.0.tbt);
+        GifRecorder::new(path, frames, every, scale, tbt, &world).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+    if config.0.stream.as_ref().is_some_and(|path| path.as_os_str() == "-") && !config.0.headless {
+        eprintln!("--stream - writes to stdout, where --text prints the world and the GUI logs: use --headless");
+        std::process::exit(1);
+    }
+    let stream = config.0.stream.as_ref().map(|path| {
+        let (format, scale, fps, tbt) = (config.0.stream_format, config.0.scale, config.0.fps, config.0.tbt);
+        VideoStream::new(path, format, scale, fps, tbt, &world).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+    let exports = Exports { stats_csv, checkpoints, png_frames, gif, stream };
 
+    if config.0.headless {
+        return run_headless(&config, world, clock, exports);
+    }
     if config.0.text {
-        print!("{}{}", termion::clear::All, termion::cursor::Goto(1, 1));
-        return for _ in 0..config.0.epoch {
-            print!("{}{}", termion::cursor::Goto(1, 1), world);
-            world.tick();
-            std::thread::sleep(Duration::from_millis(tbt));
-        };
+        let cast = config.0.cast.as_ref().map(|path| {
+            // Wide enough for the status line under small worlds.
+            let frame = world.to_string();
+            let width = frame.lines().map(|line| line.chars().count()).max().unwrap_or(0).max(80);
+            Cast::create(path, width, frame.lines().count() + 1).unwrap_or_else(|e| {
+                eprintln!("{}: {}", path.display(), e);
+                std::process::exit(1);
+            })
+        });
+        return run_text(&config, world, clock, exports, cast);
     }
 
+    // The world may not be --width by --height when it comes from a picture or a snapshot.
+    let (width, height) = ((world.width * scale) as f32, (world.height * scale) as f32);
+    let dimensions = Dimensions {
+        width: width as u16,
+        height: height as u16,
+    };
     App::new()
         .insert_resource(dimensions)
         .insert_resource(config)
-        .insert_resource(ColorGenerator { grad: colorgrad::CustomGradient::new()
-            .html_colors(match &(world.rule) {
-                Rules::HighLife => &["Pink", "HotPink", "MediumVioletRed"],
-                Rules::Conway =>  &["Lime", "Green", "DarkOliveGreen"],
-                Rules::Gravity(true) =>  &["LightCyan", "LightSteelBlue", "SteelBlue"],
-                Rules::Gravity(false) =>  &["DodgerBlue", "PowderBlue"],
-            })
-            .build().unwrap()
-        })
-        .insert_resource(WorldState { world })
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                resolution: WindowResolution::new(width, height).with_scale_factor_override(1.0),
-                title: "Cellular automata".into(),
-                ..default()
-            }),
-            ..default()
-        }))
+        .insert_resource(ColorGenerator { grad: render::gradient(&world.rule) })
+        .insert_resource(WorldState { world, paused: false, hud: true, census: CensusCache::default(), clock, exports })
+        .add_plugins(DefaultPlugins.set(window_plugin(width, height)))
         .add_startup_system(setup)
         .add_system(window_resized_event)
         .add_system(sync_dimensions)
+        .add_system(world_input)
         .add_system(world_update.run_if(on_timer(Duration::from_millis(tbt))))
         .run()
 }
 
 
+/// Runs without drawing anything, as fast as possible, for the exports.
+fn run_headless(config: &Config, mut world: World, mut clock: Option<Clock>, mut exports: Exports) {
+    while world.epoch < config.0.epoch && !exports.is_finished() {
+        world.tick();
+        tick_clock(&mut clock, &mut world);
+        exports.tick(&world);
+    }
+    save_pattern(config, &world);
+    save_snapshot(config, &world);
+}
+
+fn run_text(config: &Config, mut world: World, mut clock: Option<Clock>, mut exports: Exports, mut cast: Option<Cast>) {
+    // Raw mode lets single key presses through; without a terminal the world just plays.
+    let raw = std::io::stdout().into_raw_mode().ok();
+    let newline = if raw.is_some() { "\r\n" } else { "\n" };
+    let mut keys = raw.as_ref().map(|_| termion::async_stdin().keys());
+    let mut paused = false;
+    let mut census = CensusCache::default();
+
+    let mut frame = format!("{}{}", termion::clear::All, termion::cursor::Goto(1, 1));
+    while world.epoch < config.0.epoch {
+        frame += &format!("{}{}", termion::cursor::Goto(1, 1), world);
+        frame += &format!("\n{}{}", status(&world, paused, &mut census), termion::clear::UntilNewline);
+        print!("{}", frame.replace('\n', newline));
+        std::io::stdout().flush().ok();
+        record(&mut cast, &frame);
+        frame.clear();
+        while let Some(Ok(key)) = keys.as_mut().and_then(|keys| keys.next()) {
+            match key {
+                Key::Char('q') | Key::Ctrl('c') | Key::Esc => {
+                    save_pattern(config, &world);
+                    save_snapshot(config, &world);
+                    drop(raw);
+                    return print_census(&world, &mut cast);
+                }
+                Key::Char(' ') => paused = !paused,
+                Key::Char('s') => save_pattern(config, &world),
+                Key::Char('w') => save_snapshot(config, &world),
+                Key::Left => {
+                    paused = true;
+                    world.step_back();
+                }
+                Key::Right => {
+                    paused = true;
+                    world.tick();
+                    tick_clock(&mut clock, &mut world);
+                    exports.tick(&world);
+                }
+                _ => (),
+            }
+        }
+        if !paused {
+            world.tick();
+            tick_clock(&mut clock, &mut world);
+            exports.tick(&world);
+        }
+        std::thread::sleep(Duration::from_millis(config.0.tbt));
+    }
+    save_pattern(config, &world);
+    save_snapshot(config, &world);
+    drop(raw);
+    print_census(&world, &mut cast);
+}
+
+/// Adds what was just printed to the cast, dropping the cast if it can't be written.
+fn record(cast: &mut Option<Cast>, text: &str) {
+    if let Some(c) = cast {
+        if let Err(e) = c.write(text) {
+            eprintln!("cast: {}", e);
+            *cast = None;
+        }
+    }
+}
+
+fn save_pattern(config: &Config, world: &World) {
+    if let Some(path) = &config.0.save_pattern {
+        if let Err(e) = world.save_pattern(path) {
+            eprintln!("{}: {}", path.display(), e);
+        }
+    }
+}
+
+fn save_snapshot(config: &Config, world: &World) {
+    if let Some(path) = &config.0.save_snapshot {
+        if let Err(e) = world.save_snapshot(path) {
+            eprintln!("{}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Lists the objects the world settled into, for rules they can be recognised in.
+fn print_census(world: &World, cast: &mut Option<Cast>) {
+    if let Ok(found) = objects::analyse(world) {
+        let mut census = ObjectCensus::default();
+        census.add(&found);
+        let census = format!("\n{}", census);
+        print!("{}", census);
+        record(cast, &census);
+    }
+}
+
+
+/// The object census of the status line, kept for the whole generation as
+/// analysing the world every frame is costly.
+#[derive(Default)]
+struct CensusCache {
+    epoch: Option<u64>,
+    summary: Option<String>,
+}
+
+impl CensusCache {
+    fn summary(&mut self, world: &World) -> Option<&str> {
+        if self.epoch != Some(world.epoch) {
+            self.epoch = Some(world.epoch);
+            self.summary = world.period().and_then(|_| objects::analyse(world).ok()).map(|found| {
+                let mut census = ObjectCensus::default();
+                census.add(&found);
+                census.summary(3)
+            });
+        }
+        self.summary.as_deref()
+    }
+}
+
+fn status(world: &World, paused: bool, census: &mut CensusCache) -> String {
+    let mut status = world.stats().to_string();
+    if world.stagnation() == Some(Stagnation::Extinct) {
+        status.push_str(" extinct");
+    }
+    match world.period() {
+        Some(1) => status.push_str(" stable"),
+        Some(period) => status.push_str(&format!(" period {}", period)),
+        None => (),
+    }
+    if let Some(summary) = census.summary(world) {
+        status.push_str(&format!(" | {}", summary));
+    }
+    if paused {
+        status.push_str(" [paused]");
+    }
+    status
+}
+
+
+fn window_plugin(width: f32, height: f32) -> WindowPlugin {
+    WindowPlugin {
+        primary_window: Some(Window {
+            resolution: WindowResolution::new(width, height).with_scale_factor_override(1.0),
+            title: "Cellular automata".into(),
+            ..default()
+        }),
+        ..default()
+    }
+}
+
+
 fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
     commands.spawn(Camera2dBundle::default());
 
@@ -121,56 +540,75 @@ fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
 }
 
 
+fn world_input(keys: Res<Input<KeyCode>>, config: Res<Config>, mut world_state: ResMut<WorldState>) {
+    if keys.just_pressed(KeyCode::Space) {
+        world_state.paused = !world_state.paused;
+    }
+    if keys.just_pressed(KeyCode::H) {
+        world_state.hud = !world_state.hud;
+    }
+    if keys.just_pressed(KeyCode::S) {
+        save_pattern(&config, &world_state.world);
+    }
+    if keys.just_pressed(KeyCode::W) {
+        save_snapshot(&config, &world_state.world);
+    }
+    if keys.just_pressed(KeyCode::Left) {
+        world_state.paused = true;
+        world_state.world.step_back();
+    }
+    if keys.just_pressed(KeyCode::Right) {
+        world_state.paused = true;
+        world_state.tick();
+    }
+}
+
+
 fn world_update(
     mut images: ResMut<Assets<Image>>,
     color_generator: Res<ColorGenerator>,
     mut world_state: ResMut<WorldState>,
     dim: Res<Dimensions>,
     mut query: Query<&mut WorldRepr>,
+    mut windows: Query<&mut Window>,
 ) {
-    world_state.world.tick();
+    if !world_state.paused {
+        world_state.tick();
+    }
 
     let mut world_repr = query.single_mut();
+    let frame = render::render(&world_state.world, &color_generator.grad, dim.width as usize, dim.height as usize);
+    let mut image_byte_buffer = frame.pixels;
+
+    if world_state.hud {
+        let state = &mut *world_state;
+        draw_population(&mut image_byte_buffer, &dim, &state.world);
+        windows.single_mut().title = format!("Cellular automata - {}", status(&state.world, state.paused, &mut state.census));
+    } else {
+        windows.single_mut().title = "Cellular automata".into();
+    }
 
-    let cell_width = dim.width as usize / world_state.world.width;
-    let cell_height = dim.height as usize / world_state.world.height;
-
-    let mut image_byte_buffer = vec![0; dim.width as usize * dim.height as usize * 4];
-
-    world_state
-        .world
-        .cells
-        .iter()
-        .enumerate()
-        .for_each(|(i, cell)| {
-            let x = i % world_state.world.width;
-            let y = i / world_state.world.width;
-
-            let x = x * cell_width;
-            let y = y * cell_height;
-
-            let at = (std::cmp::min(cell.age, world_state.world.reset_at_epoch) as f64) / (world_state.world.reset_at_epoch as f64);
-            let at = if at.is_nan() {
-                0.0
-            } else { at };
+    world_repr.handle = images.set(world_repr.handle.clone(), to_image(&dim, image_byte_buffer));
+}
 
-            let rgba = color_generator.grad.at(at).to_rgba8();
 
-            let color = if cell.is_alive {
-                rgba
-            } else {
-                [0, 0, 0, 255]
-            };
+/// Population curve over the stats history, along the bottom fifth of the image.
+fn draw_population(buffer: &mut [u8], dim: &Dimensions, world: &World) {
+    let populations: Vec<usize> = world.stats_history.iter().map(|s| s.population).collect();
+    let max = populations.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let graph_height = (dim.height as usize / 5).max(1) as f32;
+    let (width, height) = (dim.width as usize, dim.height as usize);
+    let skip = populations.len().saturating_sub(width);
+    for (x, population) in populations[skip..].iter().enumerate() {
+        let y = height - 1 - ((*population as f32 / max) * (graph_height - 1.0)) as usize;
+        let i = (x + y * width) * 4;
+        buffer[i..i + 4].copy_from_slice(&[255, 255, 255, 255]);
+    }
+}
 
-            for x in x..x + cell_width {
-                for y in y..y + cell_height {
-                    let i = (x + y * dim.width as usize) * 4;
-                    image_byte_buffer[i..i + 4].copy_from_slice(&color);
-                }
-            }
-        });
 
-    let image = Image::new(
+fn to_image(dim: &Dimensions, image_byte_buffer: Vec<u8>) -> Image {
+    Image::new(
         Extent3d {
             width: dim.width as u32,
             height: dim.height as u32,
@@ -179,9 +617,7 @@ fn world_update(
         TextureDimension::D2,
         image_byte_buffer,
         TextureFormat::Rgba8UnormSrgb,
-    );
-
-    world_repr.handle = images.set(world_repr.handle.clone(), image);
+    )
 }
 
 
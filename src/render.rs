@@ -0,0 +1,162 @@
+use crate::cellular_automata::{species, triangular, Cell, Lattice, Rules, World};
+
+/// The world drawn as RGBA pixels, as the GUI shows it.
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Frame {
+        Frame {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        }
+    }
+}
+
+/// Colours of live cells, from young to old or from the first state to the last.
+pub fn gradient(rule: &Rules) -> colorgrad::Gradient {
+    colorgrad::CustomGradient::new()
+        .html_colors(match rule {
+            Rules::HighLife => &["Pink", "HotPink", "MediumVioletRed"],
+            // HighLife's rulestring, from --rules or a pattern, keeps the preset's colours.
+            Rules::LifeLike(_) if rule.rulestring() == Rules::HighLife.rulestring() => &["Pink", "HotPink", "MediumVioletRed"],
+            Rules::Conway | Rules::Immigration | Rules::QuadLife => &["Lime", "Green", "DarkOliveGreen"],
+            Rules::Gravity(true) => &["LightCyan", "LightSteelBlue", "SteelBlue"],
+            Rules::Gravity(false) => &["DodgerBlue", "PowderBlue"],
+            Rules::Table(_) => &["Yellow", "OrangeRed", "DarkRed"],
+            Rules::Hexagonal(_) => &["Gold", "Orange", "Sienna"],
+            Rules::Triangular(_) => &["Aquamarine", "Turquoise", "Teal"],
+            Rules::LifeLike(_) => &["Lime", "Green", "DarkOliveGreen"],
+            Rules::Elementary(_) => &["White", "Silver", "SlateGray"],
+            Rules::Generations(_) | Rules::LargerThanLife(_) => &["White", "DeepSkyBlue", "Navy"],
+        })
+        .build()
+        .unwrap()
+}
+
+/// Colour of a cell: its state's under multi-state rules, its species'
+/// when species compete, its age's otherwise. Dead cells are black.
+pub fn cell_color(world: &World, cell: &Cell, grad: &colorgrad::Gradient) -> [u8; 4] {
+    if !cell.is_alive {
+        return [0, 0, 0, 255];
+    }
+    let at = (std::cmp::min(cell.age, world.reset_at_epoch) as f64) / (world.reset_at_epoch as f64);
+    let at = if at.is_nan() { 0.0 } else { at };
+    match &world.rule {
+        Rules::Table(table) => match table.color(cell.state) {
+            Some([r, g, b]) => [r, g, b, 255],
+            None => grad.at(cell.state as f64 / (table.n_states - 1) as f64).to_rgba8(),
+        },
+        _ if world.species > 1 => {
+            let [r, g, b] = species::color(cell.species);
+            [r, g, b, 255]
+        }
+        rule if rule.states() > 2 => grad.at((cell.state - 1) as f64 / (rule.states() - 1) as f64).to_rgba8(),
+        _ => grad.at(at).to_rgba8(),
+    }
+}
+
+/// Up to 256 colours the world can be drawn with: black for dead cells, the
+/// rule table's and the species' own colours, then evenly spaced samples of
+/// the gradient.
+pub fn palette(world: &World, grad: &colorgrad::Gradient) -> Vec<[u8; 3]> {
+    let mut colors = vec![[0, 0, 0]];
+    if let Rules::Table(table) = &world.rule {
+        colors.extend((1..table.n_states.min(256)).filter_map(|state| table.color(state as u8)));
+    }
+    if world.species > 1 {
+        colors.extend((0..world.species).map(species::color));
+    }
+    let samples = 256 - colors.len().min(255);
+    colors.extend((0..samples).map(|i| {
+        let [r, g, b, _] = grad.at(i as f64 / (samples - 1).max(1) as f64).to_rgba8();
+        [r, g, b]
+    }));
+    colors.truncate(256);
+    colors
+}
+
+/// Draws the world stretched over `width` x `height` pixels.
+pub fn render(world: &World, grad: &colorgrad::Gradient, width: usize, height: usize) -> Frame {
+    let mut frame = Frame::new(width, height);
+
+    let cell_width = width / world.width;
+    let cell_height = height / world.height;
+    // Pointy-top hexagons: rows are 3/4 of a hexagon apart and odd rows stick
+    // out by half a hexagon on the right.
+    let hex_width = width as f32 / (world.width as f32 + 0.5);
+    let hex_row = height as f32 / (world.height as f32 + 1.0 / 3.0);
+    let tri_width = 2.0 * width as f32 / (world.width as f32 + 1.0);
+    let tri_height = height as f32 / world.height as f32;
+
+    for (i, cell) in world.cells.iter().enumerate() {
+        let x = i % world.width;
+        let y = i / world.width;
+        let color = cell_color(world, cell, grad);
+        match world.rule.lattice() {
+            Lattice::Square => {
+                let (x, y) = (x * cell_width, y * cell_height);
+                for x in x..x + cell_width {
+                    for y in y..y + cell_height {
+                        let i = (x + y * width) * 4;
+                        frame.pixels[i..i + 4].copy_from_slice(&color);
+                    }
+                }
+            }
+            Lattice::Hexagonal => fill_hexagon(&mut frame, hex_width, hex_row, x, y, color),
+            Lattice::Triangular => fill_triangle(&mut frame, tri_width, tri_height, x, y, color),
+        }
+    }
+    frame
+}
+
+/// Paints the pixels of the `[x0, x1) x [y0, y1)` box whose centre is `inside` the shape.
+pub fn fill_shape(
+    frame: &mut Frame,
+    (x0, y0, x1, y1): (f32, f32, f32, f32),
+    color: [u8; 4],
+    inside: impl Fn(f32, f32) -> bool,
+) {
+    let (x0, y0) = (x0.max(0.0) as usize, y0.max(0.0) as usize);
+    let x1 = (x1.ceil() as usize).min(frame.width);
+    let y1 = (y1.ceil() as usize).min(frame.height);
+    for py in y0..y1 {
+        for px in x0..x1 {
+            if inside(px as f32 + 0.5, py as f32 + 0.5) {
+                let i = (px + py * frame.width) * 4;
+                frame.pixels[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+fn fill_hexagon(frame: &mut Frame, hex_width: f32, hex_row: f32, x: usize, y: usize, color: [u8; 4]) {
+    let radius = hex_row * 2.0 / 3.0;
+    let cx = (x as f32 + 0.5 + 0.5 * (y % 2) as f32) * hex_width;
+    let cy = radius + y as f32 * hex_row;
+    let bbox = (cx - hex_width / 2.0, cy - radius, cx + hex_width / 2.0, cy + radius);
+    fill_shape(frame, bbox, color, |px, py| {
+        let dx = (px - cx).abs();
+        let dy = (py - cy).abs();
+        dx <= hex_width / 2.0 && dy <= radius * (1.0 - dx / hex_width)
+    });
+}
+
+/// Neighbouring triangles overlap by half their width, so the row is
+/// `(width + 1) / 2` triangles wide.
+fn fill_triangle(frame: &mut Frame, tri_width: f32, tri_height: f32, x: usize, y: usize, color: [u8; 4]) {
+    let left = x as f32 * tri_width / 2.0;
+    let top = y as f32 * tri_height;
+    let cx = left + tri_width / 2.0;
+    let up = triangular::points_up(x, y);
+    let bbox = (left, top, left + tri_width, top + tri_height);
+    fill_shape(frame, bbox, color, |px, py| {
+        let depth = (py - top) / tri_height;
+        let half_width = if up { depth } else { 1.0 - depth } * tri_width / 2.0;
+        (px - cx).abs() <= half_width
+    });
+}
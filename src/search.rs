@@ -0,0 +1,236 @@
+use std::{collections::BTreeMap, path::Path, time::Instant};
+
+use rayon::prelude::*;
+
+use crate::{
+    cellular_automata::{
+        history::History,
+        objects::{self, Kind, ObjectCensus},
+        pattern::{Format, Pattern},
+        stagnation::Detector,
+        Rules, World,
+    },
+    settings::SearchSettings,
+    Config,
+};
+
+/// Distance from the edge at which escaping spaceships are taken out of the
+/// world, before the edge breaks them into debris.
+const MARGIN: usize = 4;
+
+/// Largest group of cells near the edge checked for being a spaceship.
+const MAX_SHIP: usize = 64;
+
+/// One random soup and what it settled into.
+struct Soup {
+    seed: u64,
+    /// Live cells of the initial soup.
+    cells: Vec<bool>,
+    census: ObjectCensus,
+    /// Generations until the soup stabilised, `None` if it never did.
+    generations: Option<u64>,
+}
+
+#[derive(Default)]
+struct Tally {
+    soups: u64,
+    unstable: u64,
+    census: ObjectCensus,
+    /// Soup with the lowest seed for each periodic object, saved if the
+    /// object turns out rare.
+    firsts: BTreeMap<String, Soup>,
+    methuselahs: Vec<Soup>,
+}
+
+impl Tally {
+    fn add(mut self, soup: Soup, settings: &SearchSettings) -> Tally {
+        self.soups += 1;
+        if soup.generations.is_none() {
+            self.unstable += 1;
+        }
+        for (apgcode, example) in &soup.census.examples {
+            if example.kind != Kind::Aperiodic {
+                self.firsts.entry(apgcode.clone()).or_insert_with(|| Soup {
+                    seed: soup.seed,
+                    cells: soup.cells.clone(),
+                    census: ObjectCensus::default(),
+                    generations: soup.generations,
+                });
+            }
+        }
+        self.census.merge(soup.census);
+        if soup.generations.is_none_or(|n| n >= settings.methuselah) {
+            self.methuselahs.push(Soup { census: ObjectCensus::default(), ..soup });
+        }
+        self
+    }
+
+    fn merge(mut self, other: Tally) -> Tally {
+        self.soups += other.soups;
+        self.unstable += other.unstable;
+        self.census.merge(other.census);
+        for (apgcode, soup) in other.firsts {
+            match self.firsts.get(&apgcode) {
+                Some(kept) if kept.seed < soup.seed => (),
+                _ => {
+                    self.firsts.insert(apgcode, soup);
+                }
+            }
+        }
+        self.methuselahs.extend(other.methuselahs);
+        self
+    }
+
+    /// The first soup of each object found fewer than once in `rarity`
+    /// objects counted.
+    fn rare(&self, rarity: u64) -> impl Iterator<Item = (&String, &Soup)> {
+        let total: usize = self.census.counts.values().sum();
+        self.firsts
+            .iter()
+            .filter(move |(apgcode, _)| (self.census.counts[*apgcode] as u64).saturating_mul(rarity) < total as u64)
+    }
+}
+
+/// Runs `--soups` random soups in parallel, tallies the objects they settle
+/// into and saves the rare ones and the methuselahs.
+pub fn run(config: &Config, rule: Rules, settings: &SearchSettings) {
+    if rule.rulestring().is_none() {
+        eprintln!("search needs a life-like rule");
+        std::process::exit(1);
+    }
+    if let Err(e) = std::fs::create_dir_all(&settings.out) {
+        eprintln!("{}: {}", settings.out.display(), e);
+        std::process::exit(1);
+    }
+
+    let seed = config.0.seed.unwrap_or_else(rand::random);
+    let start = Instant::now();
+    let tally = (0..settings.soups)
+        .into_par_iter()
+        .map(|i| run_soup(&rule, config, settings, seed.wrapping_add(i)))
+        .fold(Tally::default, |tally, soup| tally.add(soup, settings))
+        .reduce(Tally::default, Tally::merge);
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let side = settings.soup_size;
+    let rare: Vec<_> = tally.rare(settings.rarity).collect();
+    for &(apgcode, soup) in &rare {
+        save(&settings.out.join(format!("{}_{}.cells", apgcode, soup.seed)), soup, side, apgcode, &rule);
+    }
+    for soup in &tally.methuselahs {
+        let lifespan = soup.generations.map_or("unstable".to_string(), |n| n.to_string());
+        let name = format!("methuselah_{}_{}", lifespan, soup.seed);
+        save(&settings.out.join(format!("{}.cells", name)), soup, side, &name, &rule);
+    }
+
+    print!("{}", tally.census);
+    println!(
+        "{} soups from seed {} in {:.1}s, {:.0} soups/s, {} did not stabilise, {} rare objects and {} methuselahs saved to {}",
+        tally.soups,
+        seed,
+        elapsed,
+        tally.soups as f64 / elapsed.max(f64::EPSILON),
+        tally.unstable,
+        rare.len(),
+        tally.methuselahs.len(),
+        settings.out.display()
+    );
+}
+
+fn run_soup(rule: &Rules, config: &Config, settings: &SearchSettings, seed: u64) -> Soup {
+    let (side, padding) = (settings.soup_size, settings.padding);
+    let mut soup = World::new(rule.clone(), side, side, 0, 0.5);
+    soup.seed(seed);
+    soup.populate();
+
+    let mut world = World::new(rule.clone(), side + 2 * padding, side + 2 * padding, 0, 0.0);
+    world.history = History::new(0);
+    world.detector = Detector::new(None, 0, config.0.max_period);
+    for (i, cell) in soup.cells.iter().enumerate() {
+        if cell.is_alive {
+            world.revive(padding + i % side, padding + i / side);
+        }
+    }
+    let mut census = ObjectCensus::default();
+    let mut edge = Vec::new();
+    while world.stagnation().is_none() && world.epoch < settings.max_generations {
+        world.tick();
+        remove_escaping(&mut world, &mut edge, &mut census);
+    }
+
+    let generations = world.stagnation().map(|_| world.epoch);
+    if let Ok(found) = objects::analyse(&world) {
+        census.add(&found);
+    }
+    Soup {
+        seed,
+        cells: soup.cells.iter().map(|c| c.is_alive).collect(),
+        census,
+        generations,
+    }
+}
+
+/// Counts and removes the spaceships within `MARGIN` of the edge. The world
+/// is only analysed when the live cells near the edge have changed since the
+/// last time, `edge`.
+fn remove_escaping(world: &mut World, edge: &mut Vec<usize>, census: &mut ObjectCensus) {
+    let (width, height) = (world.width, world.height);
+    let near_edge = |i: usize| {
+        let (x, y) = (i % width, i / width);
+        x < MARGIN || y < MARGIN || x + MARGIN >= width || y + MARGIN >= height
+    };
+    let live_near_edge = |world: &World| -> Vec<usize> {
+        (0..world.cells.len()).filter(|&i| world.cells[i].is_alive && near_edge(i)).collect()
+    };
+    let live = live_near_edge(world);
+    if live == *edge {
+        return;
+    }
+    let touching = |cells: &[(usize, usize)]| {
+        cells.len() <= MAX_SHIP && cells.iter().any(|&(x, y)| near_edge(x + y * width))
+    };
+    let Ok(found) = objects::analyse_some(world, touching) else { return };
+    let ships: Vec<_> = found
+        .into_iter()
+        .filter(|object| matches!(object.kind, Kind::Spaceship { .. }))
+        .collect();
+    for &(x, y) in ships.iter().flat_map(|ship| &ship.cells) {
+        world.kill(x, y);
+    }
+    census.add(&ships);
+    *edge = live_near_edge(world);
+}
+
+/// Writes the initial soup in plaintext `.cells` format.
+fn save(path: &Path, soup: &Soup, side: usize, name: &str, rule: &Rules) {
+    let pattern = Pattern {
+        name: Some(name.to_string()),
+        comments: vec![format!("Soup seed {}", soup.seed)],
+        rule: rule.rulestring().map(|rulestring| rulestring.to_string()),
+        width: side,
+        height: side,
+        cells: (0..soup.cells.len()).filter(|&i| soup.cells[i]).map(|i| (i % side, i / side, 1)).collect(),
+        ..Pattern::default()
+    };
+    if let Err(e) = std::fs::write(path, Format::Plaintext.write(&pattern)) {
+        eprintln!("{}: {}", path.display(), e);
+    }
+}
+
+#[test]
+fn test_remove_escaping() {
+    // A glider flying towards the bottom right corner is counted once and
+    // removed before it reaches the edge.
+    let mut world = World::new(Rules::Conway, 16, 16, 0, 0.0);
+    world.history = History::new(0);
+    for (x, y) in [(7, 6), (8, 7), (6, 8), (7, 8), (8, 8)] {
+        world.revive(x, y);
+    }
+    let (mut census, mut edge) = (ObjectCensus::default(), Vec::new());
+    for _ in 0..60 {
+        world.tick();
+        remove_escaping(&mut world, &mut edge, &mut census);
+    }
+    assert_eq!(census.summary(3), "1 glider");
+    assert!(world.cells.iter().all(|cell| !cell.is_alive));
+}
@@ -0,0 +1,133 @@
+use std::{fs::File, path::Path};
+
+use crate::cellular_automata::pattern::Pattern;
+
+/// Brightness of every pixel of a picture, from 0 for black to 1 for white.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    luma: Vec<f32>,
+}
+
+impl Image {
+    /// Reads a PNG of any colour type. Transparent pixels count as black.
+    pub fn load(path: &Path) -> Result<Image, String> {
+        let error = |e: png::DecodingError| format!("{}: {}", path.display(), e);
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(error)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(error)?;
+        let (color_type, _) = reader.output_color_type();
+        let luma = buffer[..info.buffer_size()]
+            .chunks_exact(color_type.samples())
+            .map(|pixel| {
+                let value = |c: u8| c as f32 / 255.0;
+                match *pixel {
+                    [g] => value(g),
+                    [g, a] => value(g) * value(a),
+                    [r, g, b] => 0.299 * value(r) + 0.587 * value(g) + 0.114 * value(b),
+                    [r, g, b, a] => (0.299 * value(r) + 0.587 * value(g) + 0.114 * value(b)) * value(a),
+                    _ => 0.0,
+                }
+            })
+            .collect();
+        Ok(Image {
+            width: info.width as usize,
+            height: info.height as usize,
+            luma,
+        })
+    }
+
+    /// Average brightness of the pixels under each of `width` x `height` cells.
+    fn resample(&self, width: usize, height: usize) -> Vec<f32> {
+        let span = |i: usize, cells: usize, pixels: usize| {
+            let start = i * pixels / cells;
+            start..((i + 1) * pixels / cells).max(start + 1)
+        };
+        let mut cells = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let rows = span(y, height, self.height);
+            for x in 0..width {
+                let columns = span(x, width, self.width);
+                let count = rows.len() * columns.len();
+                let sum: f32 = rows
+                    .clone()
+                    .flat_map(|py| self.luma[py * self.width..][columns.clone()].iter())
+                    .sum();
+                cells.push(sum / count as f32);
+            }
+        }
+        cells
+    }
+
+    /// The picture at `width` x `height` cells, quantised to `states` levels.
+    /// Two-state rules make the cells brighter than `threshold` live; with more
+    /// states the brightest cells take state 1 and darker ones the later
+    /// states, as the gradient colours them. Dithering spreads the rounding
+    /// error over the neighbouring cells, Floyd-Steinberg style.
+    pub fn to_pattern(&self, width: usize, height: usize, states: u16, threshold: f32, dither: bool, invert: bool) -> Pattern {
+        let mut luma = self.resample(width, height);
+        if invert {
+            luma.iter_mut().for_each(|l| *l = 1.0 - *l);
+        }
+        let top = (states.max(2) - 1) as f32;
+        let mut pattern = Pattern {
+            width,
+            height,
+            ..Pattern::default()
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let l = luma[y * width + x];
+                let level = if top == 1.0 {
+                    (l >= threshold) as u16
+                } else {
+                    (l * top).round().clamp(0.0, top) as u16
+                };
+                if level > 0 {
+                    pattern.cells.push((x, y, (top as u16 + 1 - level) as u8));
+                }
+                if dither {
+                    let error = l - level as f32 / top;
+                    for (dx, dy, weight) in [(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)] {
+                        let (nx, ny) = (x as isize + dx, y + dy);
+                        if nx >= 0 && (nx as usize) < width && ny < height {
+                            luma[ny * width + nx as usize] += error * weight / 16.0;
+                        }
+                    }
+                }
+            }
+        }
+        pattern
+    }
+}
+
+#[test]
+fn test_seed_image() {
+    use crate::{export::write_png, render::Frame};
+
+    // A left to right ramp from black to white, 8x2 pixels.
+    let mut frame = Frame::new(8, 2);
+    for (i, pixel) in frame.pixels.chunks_exact_mut(4).enumerate() {
+        let v = (i % 8 * 255 / 7) as u8;
+        pixel.copy_from_slice(&[v, v, v, 255]);
+    }
+    let path = std::env::temp_dir().join(format!("seed-{}.png", std::process::id()));
+    write_png(&path, &frame).unwrap();
+    let image = Image::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((image.width, image.height), (8, 2));
+
+    let live = |pattern: &Pattern| pattern.cells.iter().map(|&(x, _, state)| (x, state)).collect::<Vec<_>>();
+    let halves = image.to_pattern(4, 1, 2, 0.5, false, false);
+    assert_eq!(live(&halves), [(2, 1), (3, 1)]);
+    assert_eq!(live(&image.to_pattern(4, 1, 2, 0.5, false, true)), [(0, 1), (1, 1)]);
+    // Three states: black dead, grey in the dying state 2, white live.
+    assert_eq!(live(&image.to_pattern(8, 1, 3, 0.5, false, false)), [(2, 2), (3, 2), (4, 2), (5, 2), (6, 1), (7, 1)]);
+
+    let grey = Image { width: 1, height: 1, luma: vec![0.5] };
+    let dithered = grey.to_pattern(16, 16, 2, 0.5, true, false);
+    assert!((120..=136).contains(&dithered.cells.len()));
+}
@@ -1,9 +1,24 @@
-use clap::{
-    builder::{EnumValueParser, PossibleValue},
-    Parser, ValueEnum,
+use clap::{builder::PossibleValue, Args, Parser, Subcommand, ValueEnum};
+
+use super::cellular_automata::{
+    generations::GenerationsRule,
+    ltl::LtlRule,
+    rulestring::RuleString,
+    species,
+    stagnation::ResetPolicy,
+    triangular::TriangularRule,
+    world3d::{Rule3D, View3D},
+    Rules,
+};
+
+/// Hexagonal Life, the usual `hex` preset.
+const HEX: RuleString = RuleString {
+    birth: 1 << 2,
+    survival: 1 << 3 | 1 << 4,
 };
 
-use super::cellular_automata::Rules;
+/// Triangular Life: born with 4 of its 12 neighbours alive, survives with 3 to 5.
+const TRI: TriangularRule = TriangularRule::totalistic(&[4], &[3, 4, 5]);
 
 impl ValueEnum for Rules {
     fn value_variants<'a>() -> &'a [Self] {
@@ -12,6 +27,10 @@ impl ValueEnum for Rules {
             Rules::HighLife,
             Rules::Gravity(true),
             Rules::Gravity(false),
+            Rules::Immigration,
+            Rules::QuadLife,
+            Rules::Hexagonal(HEX),
+            Rules::Triangular(TRI),
         ]
     }
 
@@ -21,6 +40,17 @@ impl ValueEnum for Rules {
             Rules::HighLife => Some(PossibleValue::new("highlife")),
             Rules::Gravity(true) => Some(PossibleValue::new("snow")),
             Rules::Gravity(false) => Some(PossibleValue::new("rain")),
+            Rules::Immigration => Some(PossibleValue::new("immigration")),
+            Rules::QuadLife => Some(PossibleValue::new("quadlife")),
+            Rules::Hexagonal(HEX) => Some(PossibleValue::new("hex")),
+            Rules::Triangular(TRI) => Some(PossibleValue::new("tri")),
+            Rules::Table(_)
+            | Rules::Hexagonal(_)
+            | Rules::Triangular(_)
+            | Rules::LifeLike(_)
+            | Rules::Elementary(_)
+            | Rules::Generations(_)
+            | Rules::LargerThanLife(_) => None,
         }
     }
 
@@ -31,7 +61,30 @@ impl ValueEnum for Rules {
             "highlife" => Ok(Rules::HighLife),
             "snow" => Ok(Rules::Gravity(true)),
             "rain" => Ok(Rules::Gravity(false)),
-            _ => Err(format!("Unknown rules: {}", input)),
+            "immigration" => Ok(Rules::Immigration),
+            "quadlife" => Ok(Rules::QuadLife),
+            "hex" => Ok(Rules::Hexagonal(HEX)),
+            "tri" => Ok(Rules::Triangular(TRI)),
+            _ => {
+                if let Some(rulestring) = input.strip_suffix(['H', 'h']) {
+                    RuleString::parse(rulestring).map(Rules::Hexagonal)
+                } else if let Some(rulestring) = input.strip_suffix(['T', 't']) {
+                    TriangularRule::parse(rulestring).map(Rules::Triangular)
+                } else if let Some(number) = input.strip_prefix(['W', 'w']) {
+                    number
+                        .parse()
+                        .map(Rules::Elementary)
+                        .map_err(|_| format!("Unknown elementary rule: {}", input))
+                } else if input.starts_with('R') && input.contains(',') {
+                    LtlRule::parse(input).map(Rules::LargerThanLife)
+                } else if input.matches('/').count() == 2 {
+                    GenerationsRule::parse(input).map(Rules::Generations)
+                } else if input.contains('/') {
+                    RuleString::parse(input).map(Rules::LifeLike)
+                } else {
+                    Err(format!("Unknown rules: {}", input))
+                }
+            }
         }
     }
 }
@@ -48,7 +101,7 @@ pub struct CommandLineProvidedSettings {
     #[arg(
         long,
         default_value_t = 5,
-        help = "Scale of the world in pixels. Only works with GUI"
+        help = "Scale of the world in pixels, in the GUI and the --png-dir frames"
     )]
     pub scale: usize,
 
@@ -59,6 +112,12 @@ pub struct CommandLineProvidedSettings {
     )]
     pub text: bool,
 
+    #[arg(
+        long,
+        help = "Runs as fast as possible without a window or any output, for --record, --png-dir and the other exports. Stops after --epoch generations or once the recording is finished"
+    )]
+    pub headless: bool,
+
     #[arg(
         long,
         default_value_t = 100,
@@ -66,13 +125,19 @@ pub struct CommandLineProvidedSettings {
     )]
     pub tbt: u64,
 
-    #[arg(long, default_value = "conway", value_parser = EnumValueParser::<Rules>::new(), help = "Rules to use")]
+    #[arg(
+        long,
+        global = true,
+        default_value = "conway",
+        value_parser = |input: &str| Rules::from_str(input, false),
+        help = "Rules to use: conway, highlife, snow, rain, immigration, quadlife, hex, tri, a life-like (B36/S23), Generations (B2/S/C3), Larger than Life (R5,C0,M1,S34..58,B34..45,NM), hexagonal (B2/S34H) or triangular (B13,22/S12,21,30T) rulestring, or an elementary rule (W30)"
+    )]
     pub rules: Rules,
 
     #[arg(
         long,
         default_value_t = 1_000_000,
-        help = "Number of epochs to run before exiting. Only works with text"
+        help = "Number of epochs to run before exiting, with --text or --headless. The GUI runs until its window is closed"
     )]
     pub epoch: u64,
 
@@ -82,6 +147,255 @@ pub struct CommandLineProvidedSettings {
         help = "epoch % reset == 0 => reset the world"
     )]
     pub reset: u64,
+
+    #[arg(
+        long,
+        value_parser = ResetPolicy::parse,
+        help = "Resets a stagnating world: on-extinction, on-stable or on-period<=N"
+    )]
+    pub reset_policy: Option<ResetPolicy>,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Generations a stagnating world is still shown before --reset-policy resets it"
+    )]
+    pub reset_grace: u64,
+
+    #[arg(long, default_value_t = 30, help = "Longest oscillator period detected")]
+    pub max_period: usize,
+
+    #[arg(long, help = "Appends every generation's stats to this CSV file for plotting")]
+    pub stats_csv: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u8).range(1..=species::MAX_SPECIES as i64),
+        help = "Number of competing species, newborn cells inherit the majority colour of their parents. Only works with life-like rules"
+    )]
+    pub species: u8,
+
+    #[arg(
+        long,
+        default_value_t = 100,
+        help = "Number of past generations kept to step back through. Left steps back, Right forward and Space pauses"
+    )]
+    pub history: usize,
+
+    #[arg(
+        long,
+        help = "Golly .rule file (@TABLE) to use instead of --rules"
+    )]
+    pub rule_file: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        value_parser = Rule3D::parse,
+        help = "Runs a 3D world with a S/B/C/N rule such as 4/4/5/M, or 445, clouds, amoeba, pyroclastic"
+    )]
+    pub rules3d: Option<Rule3D>,
+
+    #[arg(
+        long,
+        default_value_t = 30,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        help = "Depth of the 3D world in cells"
+    )]
+    pub depth: usize,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = View3D::Projection,
+        help = "How the 3D world is shown. In the GUI, P switches views and Up/Down move the slice"
+    )]
+    pub view3d: View3D,
+
+    #[arg(long, help = "z layer shown by the slice view, defaults to the middle of the world")]
+    pub slice: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Writes the 3D world's voxels to this file (.vox for MagicaVoxel, text otherwise) when the run ends, or when E is pressed in the GUI"
+    )]
+    pub export_voxels: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        help = "Pattern to start from instead of a random soup, as RLE, plaintext .cells, Life 1.05/1.06 or macrocell .mc. The rule in its header is used unless --rule-file is given. Macrocells bigger than the world run unbounded with HashLife, for life-like rules, seen through a --width x --height window"
+    )]
+    pub pattern: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..),
+        help = "Generations a macrocell run with HashLife advances each frame, big steps costing little more than small ones"
+    )]
+    pub step: u64,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["pattern", "snapshot"],
+        help = "PNG picture to start from instead of a random soup, its bright parts live. With more than two states, brightness picks the state"
+    )]
+    pub seed_image: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["pattern", "snapshot", "seed_image"],
+        help = "Message to start from instead of a random soup, written in live cells with a built-in font. Lines are split on newlines"
+    )]
+    pub seed_text: Option<String>,
+
+    #[arg(long, help = "Cells per font pixel of --seed-text and --clock, as large as fits the world by default")]
+    pub font_size: Option<usize>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["pattern", "snapshot", "seed_image", "seed_text"],
+        help = "Writes the time into the world in live cells, again every minute, over whatever the rule made of it. Placed like --seed-text"
+    )]
+    pub clock: bool,
+
+    #[arg(long, requires = "clock", help = "Shows --clock as 12 hour time with AM or PM underneath")]
+    pub twelve_hour: bool,
+
+    #[arg(long, requires = "seed_image", help = "Sizes the world to --seed-image, a cell per pixel, instead of --width and --height")]
+    pub fit_image: bool,
+
+    #[arg(long, default_value_t = 0.5, help = "Brightness from 0 to 1 above which --seed-image cells live, under two-state rules")]
+    pub threshold: f32,
+
+    #[arg(long, help = "Dithers --seed-image instead of cutting it at --threshold, keeping its shades")]
+    pub dither: bool,
+
+    #[arg(long, help = "Makes the dark parts of --seed-image live instead, for dark drawings on white")]
+    pub invert: bool,
+
+    #[arg(
+        long,
+        value_parser = offset,
+        help = "Where the top left corner of --pattern or --seed-text goes, as x,y. Centred by default"
+    )]
+    pub at: Option<(usize, usize)>,
+
+    #[arg(
+        long,
+        alias = "save-rle",
+        help = "Writes the live cells to this file when the run ends, or when S is pressed, in the format its extension names: plaintext for .cells, Life 1.06 for .lif, macrocell for .mc, RLE otherwise"
+    )]
+    pub save_pattern: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        conflicts_with = "pattern",
+        help = "Resumes exactly where a --save-snapshot left off, rule, history and random state included"
+    )]
+    pub snapshot: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        help = "Writes a snapshot of the whole world to this file when the run ends, or when W is pressed. JSON for .json, binary otherwise"
+    )]
+    pub save_snapshot: Option<std::path::PathBuf>,
+
+    #[arg(long, help = "Directory every --png-every-th generation is written to as a PNG, coloured as in the GUI")]
+    pub png_dir: Option<std::path::PathBuf>,
+
+    #[arg(long, default_value_t = 1, help = "Generations between two --png-dir frames")]
+    pub png_every: u64,
+
+    #[arg(
+        long,
+        requires = "text",
+        help = "Records the --text run as an asciinema v2 cast, sized to the world rather than the terminal, which need not be attached"
+    )]
+    pub cast: Option<std::path::PathBuf>,
+
+    #[arg(long, help = "Records the run as an animated GIF, --scale pixels per cell and --tbt between frames")]
+    pub record: Option<std::path::PathBuf>,
+
+    #[arg(long, default_value_t = 100, help = "Number of frames --record captures before the GIF is finished")]
+    pub frames: usize,
+
+    #[arg(long, default_value_t = 1, help = "Generations between two --record frames")]
+    pub record_every: u64,
+
+    #[arg(
+        long,
+        help = "Streams every generation as raw video to this file, or to stdout for -, to pipe into an encoder. Stdout needs --headless"
+    )]
+    pub stream: Option<std::path::PathBuf>,
+
+    #[arg(long, value_enum, help = "Format of --stream, PPM for .ppm files and YUV4MPEG2 otherwise")]
+    pub stream_format: Option<StreamFormat>,
+
+    #[arg(long, help = "Frame rate written in the --stream header, one frame every --tbt by default")]
+    pub fps: Option<u64>,
+
+    #[arg(long, help = "Directory the world is checkpointed to every --checkpoint-every generations")]
+    pub checkpoint_dir: Option<std::path::PathBuf>,
+
+    #[arg(long, default_value_t = 1000, help = "Generations between two checkpoints")]
+    pub checkpoint_every: u64,
+
+    #[arg(long, default_value_t = 3, help = "Number of checkpoints kept per run, older ones are deleted. Each run in --checkpoint-dir is numbered apart")]
+    pub keep_checkpoints: usize,
+
+    #[arg(
+        long,
+        requires = "checkpoint_dir",
+        help = "Resumes the latest run in --checkpoint-dir from its newest checkpoint that can be read"
+    )]
+    pub resume: bool,
+
+    #[arg(long, global = true, help = "Seed for the random number generator, so runs can be reproduced")]
+    pub seed: Option<u64>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Runs random soups headlessly and tallies the objects they settle into
+    Search(SearchSettings),
+    /// Samples random rules, classifies how they behave on soups and ranks them
+    Explore(ExploreSettings),
+    /// Evolves rules towards a fitness goal with a genetic algorithm
+    Evolve(EvolveSettings),
+}
+
+#[derive(Args, Debug)]
+pub struct SearchSettings {
+    #[arg(long, default_value_t = 1000, help = "Number of soups to run")]
+    pub soups: u64,
+
+    #[arg(long, default_value_t = 16, help = "Side of the random square each soup starts as")]
+    pub soup_size: usize,
+
+    #[arg(long, default_value_t = 32, help = "Empty cells around the soup for its debris to spread into")]
+    pub padding: usize,
+
+    #[arg(long, default_value_t = 10_000, help = "Generations after which a soup that has not stabilised is given up")]
+    pub max_generations: u64,
+
+    #[arg(long, default_value_t = 2_000, help = "Soups that take this many generations to stabilise are saved as methuselahs")]
+    pub methuselah: u64,
+
+    #[arg(long, default_value_t = 1_000, help = "Objects found fewer than once in this many objects counted are rare and have their first soup saved")]
+    pub rarity: u64,
+
+    #[arg(long, default_value = "search", help = "Directory rare objects and methuselahs are saved to")]
+    pub out: std::path::PathBuf,
+}
+
+fn offset(input: &str) -> Result<(usize, usize), String> {
+    let invalid = || format!("{} is not an x,y offset", input);
+    let (x, y) = input.split_once(',').ok_or_else(invalid)?;
+    Ok((x.trim().parse().map_err(|_| invalid())?, y.trim().parse().map_err(|_| invalid())?))
 }
 
 impl Default for CommandLineProvidedSettings {
@@ -89,3 +403,113 @@ impl Default for CommandLineProvidedSettings {
         Self::parse()
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StreamFormat {
+    /// YUV4MPEG2 with full colour (4:4:4) frames, frame rate included.
+    Y4m,
+    /// Concatenated binary PPM images.
+    Ppm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RuleFamily {
+    /// Birth/survival rules on the square lattice, without B0.
+    Life,
+    /// Wolfram's one-dimensional rules.
+    Elementary,
+}
+
+#[derive(Args, Debug)]
+pub struct ExploreSettings {
+    #[arg(long, value_enum, default_value_t = RuleFamily::Life, help = "Rules to sample")]
+    pub family: RuleFamily,
+
+    #[arg(long, default_value_t = 50, help = "Number of rules to sample")]
+    pub samples: usize,
+
+    #[arg(long, default_value_t = 3, help = "Random soups each rule runs on")]
+    pub soups: u64,
+
+    #[arg(long, default_value_t = 64, help = "Side of the square world the soups fill")]
+    pub size: usize,
+
+    #[arg(long, default_value_t = 1000, help = "Generations each soup runs for at most")]
+    pub generations: u64,
+
+    #[arg(long, default_value_t = 20, help = "Number of rules shown in the table")]
+    pub top: usize,
+
+    #[arg(long, default_value_t = false, help = "Plays the most interesting rule afterwards, in the GUI or with --text")]
+    pub play: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GenomeFamily {
+    /// Birth/survival rules, without B0.
+    Life,
+    /// Birth/survival rules with dying states.
+    Generations,
+    /// Larger than Life ranges.
+    Ltl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FitnessKind {
+    /// Longest time for soups to settle.
+    Transient,
+    /// Most spaceships flying at once, life-like rules only.
+    Gliders,
+    /// Final density closest to --target-density.
+    Density,
+}
+
+#[derive(Args, Debug)]
+pub struct EvolveSettings {
+    #[arg(long, value_enum, default_value_t = GenomeFamily::Life, help = "Rules to evolve")]
+    pub family: GenomeFamily,
+
+    #[arg(long, value_enum, default_value_t = FitnessKind::Transient, help = "What the rules are selected for")]
+    pub fitness: FitnessKind,
+
+    #[arg(long, default_value_t = 0.25, help = "Density aimed for by --fitness density")]
+    pub target_density: f64,
+
+    #[arg(long, default_value_t = 40, help = "Number of rules in the population")]
+    pub population: usize,
+
+    #[arg(long, default_value_t = 20, help = "Number of rounds of selection, crossover and mutation")]
+    pub rounds: u64,
+
+    #[arg(long, default_value_t = 3, help = "Rules competing in each tournament for a parent")]
+    pub tournament: usize,
+
+    #[arg(long, default_value_t = 0.7, value_parser = probability, help = "Probability for a child to mix two parents")]
+    pub crossover: f64,
+
+    #[arg(long, default_value_t = 0.05, value_parser = probability, help = "Probability for each gene to mutate")]
+    pub mutation: f64,
+
+    #[arg(long, default_value_t = 2, help = "Random soups each rule is scored on")]
+    pub soups: u64,
+
+    #[arg(long, default_value_t = 48, help = "Side of the square world the soups fill")]
+    pub size: usize,
+
+    #[arg(long, default_value_t = 1000, help = "Generations each soup runs for at most")]
+    pub generations: u64,
+
+    #[arg(long, help = "File the population is saved to after every round, and resumed from when it exists")]
+    pub checkpoint: Option<std::path::PathBuf>,
+
+    #[arg(long, default_value_t = false, help = "Plays the fittest rule afterwards, in the GUI or with --text")]
+    pub play: bool,
+}
+
+fn probability(input: &str) -> Result<f64, String> {
+    input
+        .parse()
+        .ok()
+        .filter(|p| (0.0..=1.0).contains(p))
+        .ok_or_else(|| format!("{} is not a probability between 0 and 1", input))
+}
@@ -0,0 +1,195 @@
+use std::{path::Path, time::Duration};
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+
+use crate::{
+    canvas::{Canvas, Pixel},
+    cellular_automata::{
+        hashlife::Universe,
+        macrocell::{self, Macrocell},
+        pattern::Format,
+        Rules,
+    },
+    render::{self, fill_shape, Frame},
+    setup, sync_dimensions, to_image, window_plugin, window_resized_event, ColorGenerator, Config, Dimensions,
+    WorldRepr,
+};
+
+/// A HashLife universe seen through a `--width` x `--height` window.
+#[derive(Resource)]
+struct SparseState {
+    universe: Universe,
+    /// Top left corner of the window.
+    x: i64,
+    y: i64,
+    width: usize,
+    height: usize,
+    paused: bool,
+}
+
+impl SparseState {
+    fn canvas(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+        let lit = Pixel { r: 255, g: 255, b: 255, a: 255 };
+        for (i, alive) in self.universe.window(self.x, self.y, self.width, self.height).into_iter().enumerate() {
+            if alive {
+                canvas.draw_pixel(i % self.width, i / self.width, lit);
+            }
+        }
+        canvas
+    }
+
+    fn status(&self) -> String {
+        let mut status = format!(
+            "generation {} population {} at {},{}",
+            self.universe.generation,
+            self.universe.population(),
+            self.x,
+            self.y
+        );
+        if self.paused {
+            status.push_str(" [paused]");
+        }
+        status
+    }
+
+    /// Runs `--step` generations, stopping at `--epoch`.
+    fn tick(&mut self, config: &Config) {
+        let left = config.0.epoch.saturating_sub(self.universe.generation);
+        self.universe.step(config.0.step.min(left));
+    }
+}
+
+/// The macrocell at `path`, if it is one too big for a `width` x `height`
+/// world and so has to run in a HashLife universe instead.
+pub fn oversized(path: &Path, width: usize, height: usize) -> Result<Option<Macrocell>, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if Format::detect(path, &source) != Format::Macrocell {
+        return Ok(None);
+    }
+    let macrocell = macrocell::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok((macrocell.pattern.width > width || macrocell.pattern.height > height).then_some(macrocell))
+}
+
+/// Runs a macrocell bigger than the world with HashLife, showing the
+/// `--width` x `--height` cells around its centre.
+pub fn run(config: Config, macrocell: Macrocell, rule: Rules) {
+    let universe = rule
+        .rulestring()
+        .ok_or_else(|| format!("HashLife runs life-like rules only, not {}", rule))
+        .and_then(|rulestring| macrocell.to_universe(rulestring))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    let settings = &config.0;
+    if settings.record.is_some() || settings.png_dir.is_some() || settings.stream.is_some() || settings.stats_csv.is_some() {
+        eprintln!("--record, --png-dir, --stream and --stats-csv need a dense world, leaving them out for this macrocell");
+    }
+    let (width, height) = (settings.width, settings.height);
+    let mut state = SparseState {
+        universe,
+        x: (macrocell.pattern.width as i64 - width as i64) / 2,
+        y: (macrocell.pattern.height as i64 - height as i64) / 2,
+        width,
+        height,
+        paused: false,
+    };
+
+    if settings.headless {
+        // HashLife gets there in as few big steps as it can.
+        let left = settings.epoch.saturating_sub(state.universe.generation);
+        state.universe.step(left);
+        return save(&config, &state.universe);
+    }
+    if settings.text {
+        print!("{}{}", termion::clear::All, termion::cursor::Goto(1, 1));
+        while state.universe.generation < settings.epoch {
+            print!("{}{}", termion::cursor::Goto(1, 1), state.canvas());
+            println!("{}{}", state.status(), termion::clear::UntilNewline);
+            state.tick(&config);
+            std::thread::sleep(Duration::from_millis(settings.tbt));
+        }
+        return save(&config, &state.universe);
+    }
+
+    let tbt = settings.tbt;
+    let dimensions = Dimensions {
+        width: (width * settings.scale) as u16,
+        height: (height * settings.scale) as u16,
+    };
+    let (window_width, window_height) = (dimensions.width as f32, dimensions.height as f32);
+    App::new()
+        .insert_resource(dimensions)
+        .insert_resource(ColorGenerator { grad: render::gradient(&rule) })
+        .insert_resource(config)
+        .insert_resource(state)
+        .add_plugins(DefaultPlugins.set(window_plugin(window_width, window_height)))
+        .add_startup_system(setup)
+        .add_system(window_resized_event)
+        .add_system(sync_dimensions)
+        .add_system(sparse_input)
+        .add_system(sparse_update.run_if(on_timer(Duration::from_millis(tbt))))
+        .run()
+}
+
+/// Writes the universe to `--save-pattern`, which has to be a macrocell as
+/// no other format holds it.
+fn save(config: &Config, universe: &Universe) {
+    let Some(path) = &config.0.save_pattern else { return };
+    let result = match Format::from_extension(path) {
+        Some(Format::Macrocell) => std::fs::write(path, universe.to_macrocell()).map_err(|e| e.to_string()),
+        _ => Err("worlds run with HashLife are saved as macrocells, name a .mc file".to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}: {}", path.display(), e);
+    }
+}
+
+/// Space pauses, the arrow keys move the window a quarter of its size and S
+/// saves.
+fn sparse_input(keys: Res<Input<KeyCode>>, config: Res<Config>, mut state: ResMut<SparseState>) {
+    if keys.just_pressed(KeyCode::Space) {
+        state.paused = !state.paused;
+    }
+    let (dx, dy) = ((state.width / 4).max(1) as i64, (state.height / 4).max(1) as i64);
+    for (key, (x, y)) in [(KeyCode::Left, (-dx, 0)), (KeyCode::Right, (dx, 0)), (KeyCode::Up, (0, -dy)), (KeyCode::Down, (0, dy))] {
+        if keys.just_pressed(key) {
+            state.x += x;
+            state.y += y;
+        }
+    }
+    if keys.just_pressed(KeyCode::S) {
+        save(&config, &state.universe);
+    }
+}
+
+fn sparse_update(
+    mut images: ResMut<Assets<Image>>,
+    color_generator: Res<ColorGenerator>,
+    config: Res<Config>,
+    mut state: ResMut<SparseState>,
+    dim: Res<Dimensions>,
+    mut query: Query<&mut WorldRepr>,
+    mut windows: Query<&mut Window>,
+) {
+    if !state.paused {
+        state.tick(&config);
+    }
+
+    let mut world_repr = query.single_mut();
+    let cell_width = (dim.width as usize / state.width) as f32;
+    let cell_height = (dim.height as usize / state.height) as f32;
+    let mut frame = Frame::new(dim.width as usize, dim.height as usize);
+    frame.pixels.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 255);
+    let color = color_generator.grad.at(0.0).to_rgba8();
+    for (i, alive) in state.universe.window(state.x, state.y, state.width, state.height).into_iter().enumerate() {
+        if alive {
+            let x = (i % state.width) as f32 * cell_width;
+            let y = (i / state.width) as f32 * cell_height;
+            fill_shape(&mut frame, (x, y, x + cell_width, y + cell_height), color, |_, _| true);
+        }
+    }
+    windows.single_mut().title = format!("Cellular automata - {}", state.status());
+    world_repr.handle = images.set(world_repr.handle.clone(), to_image(&dim, frame.pixels));
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+
+use crate::{
+    cellular_automata::world3d::{Rule3D, View3D, World3D},
+    render::{fill_shape, Frame},
+    setup, sync_dimensions, to_image, window_plugin, window_resized_event, ColorGenerator, Config, Dimensions,
+    WorldRepr,
+};
+
+#[derive(Resource)]
+struct World3DState {
+    world: World3D,
+    view: View3D,
+    slice: usize,
+}
+
+pub fn run(config: Config, dimensions: Dimensions, rule: Rule3D) {
+    let settings = &config.0;
+    let tbt = settings.tbt;
+    let mut world = World3D::new(rule, settings.width, settings.height, settings.depth, 0.2);
+    world.populate();
+    let state = World3DState {
+        world,
+        view: settings.view3d,
+        slice: settings.slice.unwrap_or(settings.depth / 2),
+    };
+
+    if settings.text {
+        let mut state = state;
+        print!("{}{}", termion::clear::All, termion::cursor::Goto(1, 1));
+        for _ in 0..settings.epoch {
+            print!("{}{}", termion::cursor::Goto(1, 1), state.world.canvas(state.view, state.slice));
+            state.world.tick();
+            std::thread::sleep(Duration::from_millis(tbt));
+        }
+        export(&config, &state.world);
+        return;
+    }
+
+    let (width, height) = (dimensions.width as f32, dimensions.height as f32);
+    App::new()
+        .insert_resource(dimensions)
+        .insert_resource(config)
+        .insert_resource(ColorGenerator {
+            grad: colorgrad::CustomGradient::new()
+                .html_colors(&["MidnightBlue", "DeepSkyBlue", "White"])
+                .build()
+                .unwrap(),
+        })
+        .insert_resource(state)
+        .add_plugins(DefaultPlugins.set(window_plugin(width, height)))
+        .add_startup_system(setup)
+        .add_system(window_resized_event)
+        .add_system(sync_dimensions)
+        .add_system(world3d_input)
+        .add_system(world3d_update.run_if(on_timer(Duration::from_millis(tbt))))
+        .run()
+}
+
+fn export(config: &Config, world: &World3D) {
+    if let Some(path) = &config.0.export_voxels {
+        if let Err(e) = world.export_voxels(path) {
+            eprintln!("{}: {}", path.display(), e);
+        }
+    }
+}
+
+fn world3d_input(keys: Res<Input<KeyCode>>, config: Res<Config>, mut state: ResMut<World3DState>) {
+    if keys.just_pressed(KeyCode::P) {
+        state.view = match state.view {
+            View3D::Slice => View3D::Projection,
+            View3D::Projection => View3D::Slice,
+        };
+    }
+    if keys.just_pressed(KeyCode::Up) && state.slice + 1 < state.world.depth {
+        state.slice += 1;
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        state.slice = state.slice.saturating_sub(1);
+    }
+    if keys.just_pressed(KeyCode::E) {
+        export(&config, &state.world);
+    }
+}
+
+fn world3d_update(
+    mut images: ResMut<Assets<Image>>,
+    color_generator: Res<ColorGenerator>,
+    mut state: ResMut<World3DState>,
+    dim: Res<Dimensions>,
+    mut query: Query<&mut WorldRepr>,
+) {
+    state.world.tick();
+
+    let mut world_repr = query.single_mut();
+    let cell_width = (dim.width as usize / state.world.width) as f32;
+    let cell_height = (dim.height as usize / state.world.height) as f32;
+    let mut frame = Frame::new(dim.width as usize, dim.height as usize);
+
+    for (i, shade) in state.world.view(state.view, state.slice).into_iter().enumerate() {
+        let x = (i % state.world.width) as f32 * cell_width;
+        let y = (i / state.world.width) as f32 * cell_height;
+        let color = match shade {
+            Some(shade) => color_generator.grad.at(shade).to_rgba8(),
+            None => [0, 0, 0, 255],
+        };
+        let bbox = (x, y, x + cell_width, y + cell_height);
+        fill_shape(&mut frame, bbox, color, |_, _| true);
+    }
+
+    world_repr.handle = images.set(world_repr.handle.clone(), to_image(&dim, frame.pixels));
+}